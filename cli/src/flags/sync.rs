@@ -1,5 +1,10 @@
-use clap::Parser;
-use std::path::PathBuf;
+use super::values::{parse_duration, parse_named, parse_percent, parse_size};
+use clap::{ArgMatches, Parser};
+use cover_files::sync::{
+    exclude::FilterRule, links::LinkPolicy, mirror::DeleteTiming, select::FileType,
+    special::SpecialPolicy, target::TargetFs,
+};
+use std::{path::PathBuf, time::Duration};
 
 /// Configuration options for the `sync` command
 ///
@@ -46,4 +51,140 @@ pub struct SyncData {
     /// Show detailed logs
     #[clap(long)]
     pub verbose: bool,
+
+    /// Copy and update every file, then remove the destination entries not in source
+    #[clap(long)]
+    pub mirror: bool,
+
+    /// Remove the extra entries while mirroring before the copies, after them with a new scan, or delay them to the end
+    #[clap(long, value_name = "WHEN", value_parser = parse_named::<DeleteTiming>, default_value = "after")]
+    pub delete_timing: DeleteTiming,
+
+    /// Stop before removing anything when more than N destination entries would be removed
+    #[clap(long, value_name = "N")]
+    pub max_delete: Option<usize>,
+
+    /// Stop before removing anything when more than P percent of the destination would be removed
+    #[clap(long, value_name = "P", value_parser = parse_percent)]
+    pub max_delete_percent: Option<f64>,
+
+    /// Remove destination entries without asking, the question is only asked on a terminal
+    #[clap(long)]
+    pub yes: bool,
+
+    /// Keep syncing the rest of the tree when a file fails and report the failures at the end
+    #[clap(long)]
+    pub continue_on_error: bool,
+
+    /// Treat timestamps within this window as equal, e.g. 2s for FAT destinations
+    #[clap(long, value_name = "DUR", value_parser = parse_duration, default_value = "0")]
+    pub modify_window: Duration,
+
+    /// Compare the size and checksum of every file pair instead of the timestamps
+    #[clap(long)]
+    pub checksum: bool,
+
+    /// Number of threads that compare, hash and copy the files
+    #[clap(long, value_name = "N", default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Copy modified files whole instead of updating only their changed blocks
+    #[clap(long)]
+    pub whole_file: bool,
+
+    /// Update modified files above the delta threshold in place, writing only their changed blocks
+    #[clap(long)]
+    pub inplace: bool,
+
+    /// Update modified files above this size with a block delta with --inplace, e.g. 64M [default: 8M]
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    pub delta_threshold: Option<u64>,
+
+    /// Leave the paths matching the pattern alone on both sides, can be repeated
+    #[clap(long, value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+
+    /// Sync the paths matching the pattern even when a later exclude matches, can be repeated
+    #[clap(long, value_name = "PATTERN")]
+    pub include: Vec<String>,
+
+    /// Leave out the paths ignored by .gitignore, .git/info/exclude and the global excludes file
+    #[clap(long)]
+    pub respect_gitignore: bool,
+
+    /// Leave out the .git directories
+    #[clap(long)]
+    pub exclude_git_dir: bool,
+
+    /// Sync only the files of at least this size, e.g. 1K
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Sync only the files of at most this size, e.g. 100M
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// Sync only the files modified within this time, e.g. 7d
+    #[clap(long, value_name = "DUR", value_parser = parse_duration)]
+    pub newer_than: Option<Duration>,
+
+    /// Sync only the files modified at least this long ago, e.g. 30d
+    #[clap(long, value_name = "DUR", value_parser = parse_duration)]
+    pub older_than: Option<Duration>,
+
+    /// Sync only the files with these extensions, e.g. pdf,docx, can be repeated
+    #[clap(long, value_name = "EXT", value_delimiter = ',')]
+    pub ext: Vec<String>,
+
+    /// Sync only the files of these types: image, video, audio, document, archive or code
+    #[clap(long = "type", value_name = "TYPE", value_delimiter = ',', value_parser = parse_named::<FileType>)]
+    pub file_type: Vec<FileType>,
+
+    /// Sync the symlinks as links (preserve), as the files they point to (follow) or not at all (skip)
+    #[clap(long, value_name = "POLICY", value_parser = parse_named::<LinkPolicy>, default_value = "follow")]
+    pub links: LinkPolicy,
+
+    /// Keep the owner (as root), ACLs, extended attributes and directory metadata
+    #[clap(long)]
+    pub archive: bool,
+
+    /// Recreate the hard links between source files in the destination
+    #[clap(long)]
+    pub hard_links: bool,
+
+    /// Recreate the FIFOs, sockets and device nodes (devices as root), skip them with a warning, or fail
+    #[clap(long, value_name = "POLICY", value_parser = parse_named::<SpecialPolicy>, default_value = "skip")]
+    pub specials: SpecialPolicy,
+
+    /// Do not cross into other file systems, like /proc, network and bind mounts
+    #[clap(long)]
+    pub one_file_system: bool,
+
+    /// Map the names fat, ntfs or smb reject, like `:`, `?`, `CON` and trailing dots, to names they take
+    #[clap(long, value_name = "FS", value_parser = parse_named::<TargetFs>)]
+    pub target_fs: Option<TargetFs>,
+}
+
+impl SyncData {
+    /// Merges the include and exclude patterns in the order they are given.
+    ///
+    /// The first pattern that matches a path decides, the same as rsync, so the order
+    /// between the two options is taken from their position on the command line.
+    pub fn filter_rules(&self, matches: &ArgMatches) -> Vec<FilterRule> {
+        let mut rules: Vec<(usize, FilterRule)> = Vec::new();
+
+        if let Some(indices) = matches.indices_of("exclude") {
+            for (index, pattern) in indices.zip(&self.exclude) {
+                rules.push((index, FilterRule::Exclude(pattern.clone())));
+            }
+        }
+        if let Some(indices) = matches.indices_of("include") {
+            for (index, pattern) in indices.zip(&self.include) {
+                rules.push((index, FilterRule::Include(pattern.clone())));
+            }
+        }
+
+        rules.sort_by_key(|(index, _)| *index);
+        rules.into_iter().map(|(_, rule)| rule).collect()
+    }
 }
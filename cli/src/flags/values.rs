@@ -0,0 +1,120 @@
+use cover_files::sync::named::Named;
+use std::time::Duration;
+
+/// Parses a duration given on the command line.
+///
+/// Takes a number with an optional unit: `ns`, `us`, `ms`, `s`, `m`, `h` or `d`.
+/// A number without a unit is taken as seconds, the same as rsync.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+/// assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+/// assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+/// ```
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+
+    let seconds = match unit {
+        "ns" => number / 1_000_000_000.0,
+        "us" => number / 1_000_000.0,
+        "ms" => number / 1_000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 60.0 * 60.0,
+        "d" => number * 60.0 * 60.0 * 24.0,
+        _ => return Err(format!("unknown duration unit '{}' in '{}'", unit, value)),
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid duration '{}'", value))
+}
+
+/// Parses a size in bytes given on the command line.
+///
+/// Takes a number with an optional binary unit: `K`, `M`, `G` or `T`.
+/// A number without a unit is taken as bytes.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// assert_eq!(parse_size("512"), Ok(512));
+/// assert_eq!(parse_size("8M"), Ok(8 * 1024 * 1024));
+/// ```
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+
+    let multiplier: u64 = match unit.to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("unknown size unit '{}' in '{}'", unit, value)),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses a percentage given on the command line, with or without a trailing `%`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// assert_eq!(parse_percent("25%"), Ok(25.0));
+/// assert!(parse_percent("150").is_err());
+/// ```
+pub fn parse_percent(value: &str) -> Result<f64, String> {
+    let trimmed = value.trim();
+    let percent: f64 = trimmed
+        .strip_suffix('%')
+        .unwrap_or(trimmed)
+        .parse()
+        .map_err(|_| format!("invalid percentage '{}'", value))?;
+
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("percentage '{}' is not between 0 and 100", value));
+    }
+    Ok(percent)
+}
+
+/// Parses the name of a file type, policy, target file system or timing given on the command line.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cover_files::sync::{links::LinkPolicy, select::FileType};
+///
+/// assert_eq!(parse_named("document"), Ok(FileType::Document));
+/// assert_eq!(parse_named("preserve"), Ok(LinkPolicy::Preserve));
+/// assert!(parse_named::<FileType>("spreadsheet").is_err());
+/// ```
+pub fn parse_named<T: Named>(value: &str) -> Result<T, String> {
+    T::from_name(value.trim()).ok_or_else(|| {
+        let names: Vec<&str> = T::ALL.iter().map(|named| named.name()).collect();
+        format!(
+            "unknown {} '{}', expected one of: {}",
+            T::KIND,
+            value,
+            names.join(", ")
+        )
+    })
+}
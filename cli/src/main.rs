@@ -2,8 +2,9 @@ pub mod commands;
 pub mod flags;
 
 use crate::commands::commands::{Command, Cover};
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use cover_files::sync::sync::SyncData;
+use std::process;
 
 /// This function handles all the commands of the Cover application.
 ///
@@ -20,9 +21,15 @@ use cover_files::sync::sync::SyncData;
 /// }
 /// ```
 fn handle_commands() {
-    let cover = Cover::parse();
+    let matches = Cover::command().get_matches();
+    let cover = Cover::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     match cover.command {
         Command::Sync(s) => {
+            let filters = matches
+                .subcommand_matches("sync")
+                .map(|sync_matches| s.filter_rules(sync_matches))
+                .unwrap_or_default();
+
             let mut sync_data = SyncData {
                 source: s.source,
                 destination: s.destination,
@@ -30,8 +37,39 @@ fn handle_commands() {
                 delete: s.delete,
                 dry_run: s.dry_run,
                 verbose: s.verbose,
+                continue_on_error: s.continue_on_error,
+                modify_window: s.modify_window,
+                checksum: s.checksum,
+                jobs: s.jobs,
+                whole_file: s.whole_file,
+                inplace: s.inplace,
+                delta_threshold: s.delta_threshold,
+                filters,
+                respect_gitignore: s.respect_gitignore,
+                exclude_git_dir: s.exclude_git_dir,
+                min_size: s.min_size,
+                max_size: s.max_size,
+                newer_than: s.newer_than,
+                older_than: s.older_than,
+                extensions: s.ext,
+                file_types: s.file_type,
+                links: s.links,
+                archive: s.archive,
+                hard_links: s.hard_links,
+                specials: s.specials,
+                one_file_system: s.one_file_system,
+                target_fs: s.target_fs,
+                mirror: s.mirror,
+                delete_timing: s.delete_timing,
+                max_delete: s.max_delete,
+                max_delete_percent: s.max_delete_percent,
+                confirm_removals: !s.yes,
             };
-            sync_data.sync_options();
+
+            if let Err(e) = sync_data.sync_options() {
+                eprintln!("[ERROR]: {}", e);
+                process::exit(1);
+            }
         }
     }
 }
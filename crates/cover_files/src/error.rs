@@ -0,0 +1,134 @@
+use crate::sync::escape::escaped;
+use std::{fmt, io, path::PathBuf};
+
+/// Points to the errors that can happen while syncing.
+#[derive(Debug)]
+pub enum Error {
+    /// Source or destination directory is missing
+    MissingDirectory,
+    /// No or multiple sync options are selected
+    InvalidOptions,
+    /// Source paths that differ only by case or Unicode normalization collide in the destination
+    NameCollisions(usize),
+    /// Two destination names are mapped back to the same name with `target_fs`
+    DestNameTaken { path: PathBuf, other: PathBuf },
+    /// Given path is not a directory
+    NotADirectory(PathBuf),
+    /// Path does not belong to the directory it was listed from
+    OutsideRoot { path: PathBuf, root: PathBuf },
+    /// Reading or writing the path failed
+    Io { path: PathBuf, source: io::Error },
+    /// Include or exclude pattern can not be compiled
+    InvalidPattern { pattern: String, reason: String },
+    /// Source holds a FIFO, socket or device node and the policy is to fail
+    SpecialFile { path: PathBuf, kind: &'static str },
+    /// Sync would remove more destination entries than `max_delete` allows
+    MaxDelete { count: usize, limit: usize },
+    /// Sync would remove a larger share of the destination entries than `max_delete_percent` allows
+    MaxDeletePercent {
+        count: usize,
+        total: usize,
+        percent: f64,
+    },
+    /// Removing the destination entries is not confirmed on the terminal
+    NotConfirmed,
+    /// Sync finished but some paths failed while continuing on error
+    Incomplete(usize),
+}
+
+/// Result type returned by the sync methods.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Gives a closure that wraps an I/O error with the path it happened on.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::Error;
+    /// use std::fs::File;
+    ///
+    /// let file = File::open("source.txt").map_err(Error::io("source.txt"));
+    /// ```
+    pub fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Io { path, source }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingDirectory => write!(f, "missing source or destination directories"),
+            Error::InvalidOptions => write!(
+                f,
+                "no or multiple option(s) are selected. See 'cargo run sync --help'"
+            ),
+            Error::NameCollisions(count) => write!(
+                f,
+                "{} pair(s) of source paths would be the same path in the destination",
+                count
+            ),
+            Error::DestNameTaken { path, other } => write!(
+                f,
+                "{}: stands for the same source name as {}, so it is left out",
+                escaped(path),
+                escaped(other)
+            ),
+            Error::NotADirectory(path) => {
+                write!(f, "given path '{}' is not a directory", escaped(path))
+            }
+            Error::OutsideRoot { path, root } => write!(
+                f,
+                "path '{}' is not inside '{}'",
+                escaped(path),
+                escaped(root)
+            ),
+            Error::Io { path, source } => write!(f, "{}: {}", escaped(path), source),
+            Error::InvalidPattern { pattern, reason } => {
+                write!(f, "invalid pattern '{}': {}", pattern, reason)
+            }
+            Error::SpecialFile { path, kind } => {
+                write!(f, "{}: {} can not be synced", escaped(path), kind)
+            }
+            Error::MaxDelete { count, limit } => write!(
+                f,
+                "{} destination entr(ies) would be removed, more than the limit of {}. No entry is removed",
+                count, limit
+            ),
+            Error::MaxDeletePercent {
+                count,
+                total,
+                percent,
+            } => write!(
+                f,
+                "{} of {} destination entr(ies) would be removed, more than the limit of {}%. No entry is removed",
+                count, total, percent
+            ),
+            Error::NotConfirmed => write!(
+                f,
+                "removing the destination entries is not confirmed. No entry is removed"
+            ),
+            Error::Incomplete(count) => write!(f, "{} path(s) failed to sync", count),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<walkdir::Error> for Error {
+    fn from(err: walkdir::Error) -> Self {
+        let path = err.path().map(|p| p.to_path_buf()).unwrap_or_default();
+        Error::Io {
+            path,
+            source: err.into(),
+        }
+    }
+}
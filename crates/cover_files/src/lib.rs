@@ -1 +1,4 @@
+pub mod error;
 pub mod sync;
+
+pub use error::{Error, Result};
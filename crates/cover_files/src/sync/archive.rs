@@ -0,0 +1,230 @@
+use super::{
+    index::{EntryKind, SyncIndex},
+    plan::{OperationKind, SyncOperation},
+    special::set_path_times,
+    sync::SyncData,
+};
+use crate::error::{Error, Result};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsString,
+    fs::{Permissions, metadata, set_permissions, symlink_metadata},
+    io,
+    os::unix::fs::{MetadataExt, PermissionsExt, chown, lchown},
+    path::{Path, PathBuf},
+};
+
+/// Checks whether the sync runs as root, the only user that can give files to others.
+pub fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and never fails
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Metadata that archive mode keeps besides the content and timestamps.
+///
+/// POSIX ACLs are kept by the kernel as the `system.posix_acl_access` and
+/// `system.posix_acl_default` extended attributes, so they are copied with the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attributes {
+    /// Permission bits with the setuid, setgid and sticky bits
+    pub mode: u32,
+    /// User and group ids, only read when running as root
+    pub owner: Option<(u32, u32)>,
+    /// Extended attributes by name, empty when the file system has none
+    pub xattrs: BTreeMap<OsString, Vec<u8>>,
+}
+
+impl Attributes {
+    /// Reads the attributes of a file or directory, following links.
+    ///
+    /// Takes:
+    /// - Path of the entry
+    ///
+    /// Returns:
+    /// - Permission bits, owner and extended attributes of the entry
+    /// - Error if the entry or its extended attributes can not be read
+    pub fn of(path: &Path) -> Result<Self> {
+        let meta = metadata(path).map_err(Error::io(path))?;
+
+        let mut xattrs = BTreeMap::new();
+        for name in list_xattrs(path)? {
+            if let Some(value) = xattr::get_deref(path, &name).map_err(Error::io(path))? {
+                xattrs.insert(name, value);
+            }
+        }
+
+        Ok(Attributes {
+            mode: meta.mode() & 0o7777,
+            owner: is_root().then(|| (meta.uid(), meta.gid())),
+            xattrs,
+        })
+    }
+
+    /// Gives the attributes to another file or directory.
+    ///
+    /// The owner is set first, since changing it clears the setuid and setgid bits.
+    /// Extended attributes the entry has and these do not are removed.
+    pub fn apply(&self, path: &Path) -> Result<()> {
+        if let Some((uid, gid)) = self.owner {
+            chown(path, Some(uid), Some(gid)).map_err(Error::io(path))?;
+        }
+        set_permissions(path, Permissions::from_mode(self.mode)).map_err(Error::io(path))?;
+
+        for name in list_xattrs(path)? {
+            if !self.xattrs.contains_key(&name) {
+                xattr::remove_deref(path, &name).map_err(Error::io(path))?;
+            }
+        }
+        for (name, value) in &self.xattrs {
+            xattr::set_deref(path, name, value).map_err(Error::io(path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Gives a link the owner of the source link, when running as root, and its timestamps.
+///
+/// Neither link is followed. Links have no permissions of their own, and Linux keeps no
+/// user extended attributes on them, so nothing else is copied.
+pub(crate) fn copy_link_metadata(src_path: &Path, dest_path: &Path) -> Result<()> {
+    let src_meta = symlink_metadata(src_path).map_err(Error::io(src_path))?;
+    if is_root() {
+        lchown(dest_path, Some(src_meta.uid()), Some(src_meta.gid()))
+            .map_err(Error::io(dest_path))?;
+    }
+    set_path_times(dest_path, &src_meta).map_err(Error::io(dest_path))
+}
+
+/// Gives the owner of a link itself, only read when running as root.
+fn link_owner(path: &Path) -> Result<Option<(u32, u32)>> {
+    let meta = symlink_metadata(path).map_err(Error::io(path))?;
+    Ok(is_root().then(|| (meta.uid(), meta.gid())))
+}
+
+/// Lists the names of the extended attributes, none when the file system has no support.
+fn list_xattrs(path: &Path) -> Result<Vec<OsString>> {
+    match xattr::list_deref(path) {
+        Ok(names) => Ok(names.collect()),
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => Ok(Vec::new()),
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => Ok(Vec::new()),
+        Err(e) => Err(Error::io(path)(e)),
+    }
+}
+
+/// Implementation for keeping the ownership, ACLs and extended attributes in archive mode.
+impl SyncData {
+    /// Copies the archive attributes of a source entry to a destination entry.
+    pub(crate) fn copy_attributes(&self, src_path: &Path, dest_path: &Path) -> Result<()> {
+        Attributes::of(src_path)?.apply(dest_path)
+    }
+
+    /// Checks whether an entry on both sides differs only in its metadata.
+    ///
+    /// Takes:
+    /// - Relative path of a file, directory or preserved link that is on both sides
+    /// - Index of the source and destination
+    ///
+    /// Returns:
+    /// - Boolean to show the timestamps, permissions, owner or extended attributes differ
+    /// - Error if the attributes of either side can not be read
+    pub(crate) fn attributes_differ(&self, path: &Path, index: &SyncIndex) -> Result<bool> {
+        let (Some(src), Some(dest)) = (index.source.get(path), index.destination.get(path)) else {
+            return Ok(false);
+        };
+
+        if self.is_newer(src.modified, dest.modified) || self.is_newer(dest.modified, src.modified)
+        {
+            return Ok(true);
+        }
+        if src.kind == EntryKind::Symlink {
+            return Ok(link_owner(&index.source.absolute(path))?
+                != link_owner(&index.destination.absolute(path))?);
+        }
+        Ok(Attributes::of(&index.source.absolute(path))?
+            != Attributes::of(&index.destination.absolute(path))?)
+    }
+
+    /// Plans the metadata updates of archive mode for an existing list of operations.
+    ///
+    /// Takes:
+    /// - Index of the source and destination
+    /// - Operations that change the content of the destination
+    /// - Failures of the comparisons, when the sync continues on error
+    ///
+    /// Returns:
+    /// - Metadata operations for the entries whose content is in sync but whose metadata
+    ///   is not, and for every directory that is created or has its entries changed
+    /// - Error if the attributes can not be read, unless the sync continues on error
+    ///
+    /// The directories come last and deepest first, since adding or removing an entry
+    /// changes the timestamps of the directory it is in.
+    pub(crate) fn plan_attributes(
+        &self,
+        index: &SyncIndex,
+        operations: &[SyncOperation],
+        failures: &mut Vec<Error>,
+    ) -> Result<Vec<SyncOperation>> {
+        let src = &index.source;
+        let dest = &index.destination;
+
+        let changed: BTreeSet<&PathBuf> = operations
+            .iter()
+            .filter(|op| op.kind != OperationKind::Mkdir)
+            .map(|op| &op.path)
+            .collect();
+        let mut touched_dirs: BTreeSet<PathBuf> = BTreeSet::new();
+        for op in operations {
+            let parents = op
+                .path
+                .parent()
+                .into_iter()
+                .chain(op.from.as_deref().and_then(Path::parent));
+            touched_dirs.extend(parents.map(Path::to_path_buf));
+            if op.kind == OperationKind::Mkdir {
+                touched_dirs.insert(op.path.clone());
+            }
+        }
+
+        let common: Vec<&PathBuf> = src
+            .entries
+            .iter()
+            .filter(|(path, entry)| {
+                // Opening a FIFO or device to set its times has side effects
+                entry.kind != EntryKind::Special
+                    && !changed.contains(path)
+                    && dest.get(path).is_some_and(|other| other.kind == entry.kind)
+            })
+            .map(|(path, _)| path)
+            .collect();
+        let compared = self.run_parallel(&common, |path| self.attributes_differ(path, index));
+
+        let mut files = Vec::new();
+        for (path, differs) in common.into_iter().zip(compared) {
+            if let Some(true) = self.recover(differs, failures)? {
+                if src.is_dir(path) {
+                    touched_dirs.insert(path.clone());
+                } else {
+                    files.push(path.clone());
+                }
+            }
+        }
+
+        let mut dirs: Vec<PathBuf> = touched_dirs
+            .into_iter()
+            .filter(|dir| src.is_dir(dir))
+            .collect();
+        dirs.sort_by_key(|dir| Reverse(dir.components().count()));
+
+        Ok(files
+            .into_iter()
+            .chain(dirs)
+            .map(|path| SyncOperation {
+                kind: OperationKind::Attrs,
+                path,
+                size: 0,
+                from: None,
+            })
+            .collect())
+    }
+}
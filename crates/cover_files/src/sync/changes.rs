@@ -1,12 +1,32 @@
-use super::sync::SyncData;
+use super::{
+    index::{EntryKind, SyncIndex},
+    report::SyncReport,
+    sync::SyncData,
+};
+use crate::error::{Error, Result};
 use std::{
     fs::File,
     io::{BufReader, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 const CHUNK_SIZE: usize = 8 * 1024;
 
+/// Gives the answer of a query, or prints the paths that failed while continuing on error
+/// and returns `Error::Incomplete` with their count.
+pub(crate) fn finish_query<T>(value: T, failures: Vec<Error>) -> Result<T> {
+    if failures.is_empty() {
+        return Ok(value);
+    }
+
+    let report = SyncReport {
+        failures,
+        ..Default::default()
+    };
+    report.print_failures();
+    Err(Error::Incomplete(report.failures.len()))
+}
+
 /// Implementation of the source and destination files that are created or modified.
 impl SyncData {
     /// Verifies whether the source and destination file content is equal.
@@ -16,6 +36,7 @@ impl SyncData {
     ///
     /// Returns:
     /// - Boolean to show the comparison status
+    /// - Error if any of the files can not be read
     ///
     /// Opens the source and destination files and reads them through buffer. Compares their content to see if there is inequality.
     ///
@@ -32,15 +53,17 @@ impl SyncData {
     ///     delete: false,
     ///     dry_run: false,
     ///     verbose: false,
+    ///     ..Default::default()
     /// };
     ///
-    /// let equal = sync.files_are_equal(&PathBuf::from("source.txt"), &PathBuf::from("destination.txt"));
+    /// let equal = sync
+    ///     .files_are_equal(&PathBuf::from("source.txt"), &PathBuf::from("destination.txt"))
+    ///     .expect("[ERROR]: failed to compare the files");
     /// assert!(equal);
     /// ```
-    pub fn files_are_equal(&self, src_file: &PathBuf, dest_file: &PathBuf) -> bool {
-        let src_open = File::open(src_file).expect("[ERROR]: failed to open the source file");
-        let dest_open =
-            File::open(dest_file).expect("[ERROR]: failed to open the destination file");
+    pub fn files_are_equal(&self, src_file: &PathBuf, dest_file: &PathBuf) -> Result<bool> {
+        let src_open = File::open(src_file).map_err(Error::io(src_file))?;
+        let dest_open = File::open(dest_file).map_err(Error::io(dest_file))?;
 
         let mut src_reader = BufReader::new(src_open);
         let mut dest_reader = BufReader::new(dest_open);
@@ -49,23 +72,21 @@ impl SyncData {
         let mut buf_dest = [0u8; CHUNK_SIZE];
 
         loop {
-            let src_content = src_reader
-                .read(&mut buf_src)
-                .expect("[ERROR]: failed to read the file");
+            let src_content = src_reader.read(&mut buf_src).map_err(Error::io(src_file))?;
             let dest_content = dest_reader
                 .read(&mut buf_dest)
-                .expect("[ERROR]: failed to read the file");
+                .map_err(Error::io(dest_file))?;
 
             if src_content != dest_content {
-                return false;
+                return Ok(false);
             }
 
             if src_content == 0 {
-                return true;
+                return Ok(true);
             }
 
             if buf_src[..src_content] != buf_dest[..dest_content] {
-                return false;
+                return Ok(false);
             }
         }
     }
@@ -74,10 +95,12 @@ impl SyncData {
     ///
     /// Resturns:
     /// - Boolean value as a creation sign
+    /// - Error if the files can not be listed
     ///
-    /// Lists the source files and directories for checking the creation, finds the relative directories and files and joins them with the destination.
+    /// Scans the source once and checks whether every relative path of it exists in the destination.
     ///
-    /// Checks the existance of those files in the destination.
+    /// The paths that fail while continuing on error are printed at the end and
+    /// `Error::Incomplete` is returned with their count.
     ///
     /// # Example
     ///
@@ -92,46 +115,29 @@ impl SyncData {
     ///     delete: false,
     ///     dry_run: false,
     ///     verbose: false,
+    ///     ..Default::default()
     /// };
     ///
-    /// let created = sync.src_file_created();
+    /// let created = sync.src_file_created().expect("[ERROR]: failed to check the creation");
     /// assert!(created);
     /// ```
-    pub fn src_file_created(&self) -> bool {
-        let dir_path = self.list_src_dirs();
-        let file_path = self.list_src_files();
-        let mut not_found = false;
-
-        for entry in dir_path {
-            if entry == self.source {
-                continue;
-            }
-
-            let relative_dir = entry.strip_prefix(&self.source);
-            match relative_dir {
-                Ok(dir) => {
-                    let relative_path = self.destination.join(dir);
-                    if !relative_path.exists() {
-                        not_found = true;
-                    }
-                }
-                Err(e) => eprintln!("[ERROR]: {}", e),
-            }
-        }
+    pub fn src_file_created(&self) -> Result<bool> {
+        let mut failures = Vec::new();
+        let source = self.scan_tree(&self.source, &mut failures)?;
+        let created = source
+            .entries
+            .keys()
+            .any(|path| !self.dest_path(path).exists());
+        finish_query(created, failures)
+    }
 
-        for entry in file_path {
-            let relative_file = entry.strip_prefix(&self.source);
-            match relative_file {
-                Ok(file) => {
-                    let relative_path = self.destination.join(file);
-                    if !relative_path.exists() {
-                        not_found = true;
-                    }
-                }
-                Err(e) => eprintln!("[ERROR]: {}", e),
-            }
-        }
-        not_found
+    /// Checks whether any source file or directory is missing in the destination index.
+    pub fn src_created_in(&self, index: &SyncIndex) -> bool {
+        index
+            .source
+            .entries
+            .keys()
+            .any(|path| !index.destination.contains(path))
     }
 
     /// Checks whether the source file is modified or not.
@@ -139,9 +145,11 @@ impl SyncData {
     /// Returns:
     /// - The vector pathbuf for modified files.
     /// - Boolean value as modification sign.
+    /// - Error if the files can not be listed or read.
+    /// - `Error::Incomplete` with the count of the files that failed while continuing on error.
     ///
-    /// Lists the source and destination files, checks the timestamp, declares a variable for storage and get the source and destination timestamp.
-    /// Finds the largest timestamp and reads the content of files. Compares the content to check the modification.
+    /// Scans the source and destination once and compares the indexed timestamps.
+    /// Reads the content of the files whose timestamps differ to check the modification.
     ///
     /// # Example
     ///
@@ -156,53 +164,64 @@ impl SyncData {
     ///     delete: false,
     ///     dry_run: false,
     ///     verbose: false,
+    ///     ..Default::default()
     /// };
     ///
     /// let modified_file = PathBuf::from("filename.txt");
-    /// let (file_modified, is_modified) = sync.src_file_modified();
+    /// let (file_modified, is_modified) = sync
+    ///     .src_file_modified()
+    ///     .expect("[ERROR]: failed to check the modification");
     /// for file in file_modified {
     ///     assert_eq!(file.to_string_lossy(), modified_file.to_string_lossy());
     /// }
     /// assert!(is_modified);
     /// ```
-    pub fn src_file_modified(&self) -> (Vec<PathBuf>, bool) {
-        let src_files = self.list_src_files();
-        let dest_files = self.list_dest_files();
-
-        let src_timestamp = self.file_timestamp(src_files, &self.source);
-        let dest_timestamp = self.file_timestamp(dest_files, &self.destination);
-
-        let mut modified_files: Vec<PathBuf> = Vec::new();
+    pub fn src_file_modified(&self) -> Result<(Vec<PathBuf>, bool)> {
+        let mut index = self.scan()?;
+        let mut failures = std::mem::take(&mut index.failures);
+        let modified = self.src_modified_in(&index, &mut failures)?;
+        finish_query(modified, failures)
+    }
 
-        for (path, src_time) in &src_timestamp {
-            let dest = dest_timestamp.get(path);
-            match dest {
-                Some(dest_time) => {
-                    if src_time > dest_time {
-                        let src_path = self.source.join(path);
-                        let dest_path = self.destination.join(path);
+    /// Finds the files of the index that are modified in the source.
+    ///
+    /// A file that can not be compared is pushed to the failures when the sync continues on error.
+    pub fn src_modified_in(
+        &self,
+        index: &SyncIndex,
+        failures: &mut Vec<Error>,
+    ) -> Result<(Vec<PathBuf>, bool)> {
+        let mut candidates: Vec<&PathBuf> = Vec::new();
 
-                        if !self.files_are_equal(&src_path, &dest_path) {
-                            modified_files.push(path.clone());
-                        }
-                    }
-                }
-                None => {}
+        for (path, src) in &index.source.entries {
+            // In the checksum mode every pair is compared, and the source side takes the equal
+            // timestamps, also of the files whose size changed within the modify window
+            if src.kind == EntryKind::File
+                && let Some(dest) = index.destination.get(path)
+                && dest.kind == EntryKind::File
+                && (self.is_newer(src.modified, dest.modified)
+                    || ((self.checksum || src.size != dest.size)
+                        && !self.is_newer(dest.modified, src.modified)))
+            {
+                candidates.push(path);
             }
         }
 
+        let modified_files = self.differing_files(index, candidates, failures)?;
         let file_modified = !modified_files.is_empty();
-        (modified_files, file_modified)
+        Ok((modified_files, file_modified))
     }
 
     /// Checks whether the destination file is created or not.
     ///
     /// Resturns:
     /// - Boolean value as a creation sign
+    /// - Error if the files can not be listed
     ///
-    /// Lists the source files and directories for checking creation, finds the relative directories and files and joins them with the source.
+    /// Scans the destination once and checks whether every relative path of it exists in the source.
     ///
-    /// Checks the existance of those files in the source.
+    /// The paths that fail while continuing on error are printed at the end and
+    /// `Error::Incomplete` is returned with their count.
     ///
     /// # Example
     ///
@@ -217,46 +236,29 @@ impl SyncData {
     ///     delete: false,
     ///     dry_run: false,
     ///     verbose: false,
+    ///     ..Default::default()
     /// };
     ///
-    /// let created = sync.dest_file_created();
+    /// let created = sync.dest_file_created().expect("[ERROR]: failed to check the creation");
     /// assert!(created);
     /// ```
-    pub fn dest_file_created(&self) -> bool {
-        let dir_path = self.list_dest_dirs();
-        let file_path = self.list_dest_files();
-        let mut not_found = false;
-
-        for entry in dir_path {
-            if entry == self.destination {
-                continue;
-            }
-
-            let relative_dir = entry.strip_prefix(&self.destination);
-            match relative_dir {
-                Ok(dir) => {
-                    let relative_path = self.source.join(dir);
-                    if !relative_path.exists() {
-                        not_found = true;
-                    }
-                }
-                Err(e) => eprintln!("[ERROR]: {}", e),
-            }
-        }
+    pub fn dest_file_created(&self) -> Result<bool> {
+        let mut failures = Vec::new();
+        let destination = self.scan_tree(&self.destination, &mut failures)?;
+        let created = destination
+            .entries
+            .keys()
+            .any(|path| !self.source.join(path).exists());
+        finish_query(created, failures)
+    }
 
-        for entry in file_path {
-            let relative_file = entry.strip_prefix(&self.destination);
-            match relative_file {
-                Ok(file) => {
-                    let relative_path = self.source.join(file);
-                    if !relative_path.exists() {
-                        not_found = true;
-                    }
-                }
-                Err(e) => eprintln!("[ERROR]: {}", e),
-            }
-        }
-        not_found
+    /// Checks whether any destination file or directory is missing in the source index.
+    pub fn dest_created_in(&self, index: &SyncIndex) -> bool {
+        index
+            .destination
+            .entries
+            .keys()
+            .any(|path| !index.source.contains(path))
     }
 
     /// Checks whether the destination file is modified or not.
@@ -264,9 +266,11 @@ impl SyncData {
     /// Returns:
     /// - The vector pathbuf for modified files.
     /// - Boolean value as modification sign.
+    /// - Error if the files can not be listed or read.
+    /// - `Error::Incomplete` with the count of the files that failed while continuing on error.
     ///
-    /// Lists the source and destination files, checks the timestamp, declares a variable for storage and get the source and destination timestamp.
-    /// Finds the largest timestamp and reads the content of files. Compares the content to check the modification.
+    /// Scans the source and destination once and compares the indexed timestamps.
+    /// Reads the content of the files whose timestamps differ to check the modification.
     ///
     /// # Example
     ///
@@ -281,42 +285,112 @@ impl SyncData {
     ///     delete: false,
     ///     dry_run: false,
     ///     verbose: false,
+    ///     ..Default::default()
     /// };
     ///
     /// let modified_file = PathBuf::from("filename.txt");
-    /// let (file_modified, is_modified) = sync.dest_file_modified();
+    /// let (file_modified, is_modified) = sync
+    ///     .dest_file_modified()
+    ///     .expect("[ERROR]: failed to check the modification");
     /// for file in file_modified {
     ///     assert_eq!(file.to_string_lossy(), modified_file.to_string_lossy());
     /// }
     /// assert!(is_modified);
     /// ```
-    pub fn dest_file_modified(&self) -> (Vec<PathBuf>, bool) {
-        let src_files = self.list_src_files();
-        let dest_files = self.list_dest_files();
+    pub fn dest_file_modified(&self) -> Result<(Vec<PathBuf>, bool)> {
+        let mut index = self.scan()?;
+        let mut failures = std::mem::take(&mut index.failures);
+        let modified = self.dest_modified_in(&index, &mut failures)?;
+        finish_query(modified, failures)
+    }
+
+    /// Finds the files of the index that are modified in the destination.
+    ///
+    /// A file that can not be compared is pushed to the failures when the sync continues on error.
+    pub fn dest_modified_in(
+        &self,
+        index: &SyncIndex,
+        failures: &mut Vec<Error>,
+    ) -> Result<(Vec<PathBuf>, bool)> {
+        let mut candidates: Vec<&PathBuf> = Vec::new();
+
+        for (path, dest) in &index.destination.entries {
+            if dest.kind == EntryKind::File
+                && let Some(src) = index.source.get(path)
+                && src.kind == EntryKind::File
+                && self.is_newer(dest.modified, src.modified)
+            {
+                candidates.push(path);
+            }
+        }
+
+        let modified_files = self.differing_files(index, candidates, failures)?;
+        let file_modified = !modified_files.is_empty();
+        Ok((modified_files, file_modified))
+    }
+
+    /// Checks whether a file that exists on both sides has changed.
+    ///
+    /// Takes:
+    /// - Path of the file relative to the source and destination
+    /// - Index that holds the timestamps of both sides
+    ///
+    /// The file is changed when the sizes differ, or when its timestamps differ by more
+    /// than the modify window and the content is not equal. In the checksum mode the timestamps are ignored
+    /// and the size and checksum of every pair are compared.
+    pub(crate) fn file_changed(&self, path: &Path, index: &SyncIndex) -> Result<bool> {
+        let (Some(src), Some(dest)) = (index.source.get(path), index.destination.get(path)) else {
+            return Ok(false);
+        };
+
+        // A different size is a change whatever the timestamps, like the quick check of rsync
+        if src.size != dest.size {
+            return Ok(true);
+        }
 
-        let src_timestamp = self.file_timestamp(src_files, &self.source);
-        let dest_timestamp = self.file_timestamp(dest_files, &self.destination);
+        if !self.checksum
+            && !self.is_newer(src.modified, dest.modified)
+            && !self.is_newer(dest.modified, src.modified)
+        {
+            return Ok(false);
+        }
 
-        let mut modified_files: Vec<PathBuf> = Vec::new();
+        self.content_differs(index, path)
+    }
 
-        for (path, dest_time) in &dest_timestamp {
-            let src = src_timestamp.get(path);
-            match src {
-                Some(src_time) => {
-                    if dest_time > src_time {
-                        let src_path = self.source.join(path);
-                        let dest_path = self.destination.join(path);
+    /// Compares the content of the candidate files on up to `jobs` threads and keeps
+    /// the ones that differ, in their order.
+    fn differing_files(
+        &self,
+        index: &SyncIndex,
+        candidates: Vec<&PathBuf>,
+        failures: &mut Vec<Error>,
+    ) -> Result<Vec<PathBuf>> {
+        let compared = self.run_parallel(&candidates, |path| self.content_differs(index, path));
 
-                        if !self.files_are_equal(&src_path, &dest_path) {
-                            modified_files.push(path.clone());
-                        }
-                    }
-                }
-                None => {}
+        let mut modified_files = Vec::new();
+        for (path, differs) in candidates.into_iter().zip(compared) {
+            if let Some(true) = self.recover(differs, failures)? {
+                modified_files.push(path.clone());
             }
         }
+        Ok(modified_files)
+    }
 
-        let file_modified = !modified_files.is_empty();
-        (modified_files, file_modified)
+    /// Compares the content of a file pair with the checksum or byte by byte.
+    ///
+    /// The sizes are taken from the index, so files of different sizes are not read.
+    fn content_differs(&self, index: &SyncIndex, path: &Path) -> Result<bool> {
+        if index.source.size_of(path) != index.destination.size_of(path) {
+            return Ok(true);
+        }
+
+        let src_path = index.source.absolute(path);
+        let dest_path = index.destination.absolute(path);
+        if self.checksum {
+            Ok(!self.checksums_match(&src_path, &dest_path)?)
+        } else {
+            Ok(!self.files_are_equal(&src_path, &dest_path)?)
+        }
     }
 }
@@ -0,0 +1,65 @@
+use super::sync::SyncData;
+use crate::error::{Error, Result};
+use std::{
+    fs::{File, metadata},
+    path::Path,
+};
+
+/// Name of the hash that is used in the checksum mode.
+pub const CHECKSUM_ALGORITHM: &str = "BLAKE3";
+
+/// Implementation of the checksum based change detection.
+impl SyncData {
+    /// Calculates the checksum of a file.
+    ///
+    /// Takes:
+    /// - Path of the file to hash
+    ///
+    /// Returns:
+    /// - BLAKE3 hash of the file content
+    /// - Error if the file can not be read
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::PathBuf;
+    ///
+    /// let sync = SyncData {
+    ///     checksum: true,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let hash = sync
+    ///     .file_checksum(&PathBuf::from("source.txt"))
+    ///     .expect("[ERROR]: failed to hash the file");
+    /// assert_eq!(hash.len(), 32);
+    /// ```
+    pub fn file_checksum(&self, path: &Path) -> Result<[u8; 32]> {
+        let file = File::open(path).map_err(Error::io(path))?;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update_reader(file).map_err(Error::io(path))?;
+        Ok(*hasher.finalize().as_bytes())
+    }
+
+    /// Verifies whether the source and destination files have the same checksum.
+    ///
+    /// Takes:
+    /// - Source and Destination file for comparison
+    ///
+    /// Returns:
+    /// - Boolean to show whether the checksums match
+    /// - Error if any of the files can not be read
+    ///
+    /// Compares the sizes first, so the files are only hashed when their sizes are equal.
+    pub fn checksums_match(&self, src_file: &Path, dest_file: &Path) -> Result<bool> {
+        let src_size = metadata(src_file).map_err(Error::io(src_file))?.len();
+        let dest_size = metadata(dest_file).map_err(Error::io(dest_file))?.len();
+
+        if src_size != dest_size {
+            return Ok(false);
+        }
+
+        Ok(self.file_checksum(src_file)? == self.file_checksum(dest_file)?)
+    }
+}
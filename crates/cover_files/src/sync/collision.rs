@@ -0,0 +1,250 @@
+use super::{
+    escape::escaped,
+    index::{SyncIndex, TreeIndex},
+    sync::SyncData,
+    target::{TargetFs, encode_name},
+};
+use crate::error::{Error, Result};
+use std::{
+    collections::BTreeMap,
+    ffi::{OsStr, OsString},
+    fs::{OpenOptions, remove_file, symlink_metadata},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process,
+};
+use unicode_normalization::UnicodeNormalization;
+
+/// Ways a file system treats different names as the same entry.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NameFolding {
+    /// Names that differ only by case are the same, like on FAT, NTFS, SMB and APFS
+    pub case: bool,
+    /// Names that differ only by their Unicode normalization (NFC or NFD) are the same, like on APFS and HFS+
+    pub normalization: bool,
+    /// Names are mapped for this file system first, so names that map to the same one are the same
+    pub target: Option<TargetFs>,
+}
+
+impl NameFolding {
+    /// Finds the way a directory treats names by creating a probe file in it.
+    ///
+    /// Takes:
+    /// - Directory to check
+    ///
+    /// Returns:
+    /// - Folding of the directory
+    /// - Error if the probe can not be created, like in a read-only directory
+    ///
+    /// The probe has an upper case letter and a decomposed `é`, then the lower case and
+    /// composed names are looked up. It is removed right after.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::collision::NameFolding;
+    /// use std::path::Path;
+    ///
+    /// let folding = NameFolding::of(Path::new("/mnt/usb")).expect("[ERROR]: failed to probe");
+    /// println!("case-insensitive: {}", folding.case);
+    /// ```
+    pub fn of(dir: &Path) -> Result<NameFolding> {
+        let name = |letters: &str| dir.join(format!(".cover-probe-{}-{}", process::id(), letters));
+        let probe = name("E\u{301}X");
+
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&probe)
+            .map_err(Error::io(&probe))?;
+        let folding = NameFolding {
+            case: symlink_metadata(name("e\u{301}x")).is_ok(),
+            normalization: symlink_metadata(name("\u{c9}X")).is_ok(),
+            target: None,
+        };
+        let _ = remove_file(&probe);
+        Ok(folding)
+    }
+
+    /// Infers the way a tree treats names from the entries it already holds, without writing.
+    ///
+    /// Takes:
+    /// - Index of the tree
+    ///
+    /// Returns:
+    /// - Folding found by looking up each name with its case swapped and in the other Unicode
+    ///   normalization, none of the ways when no name has another form
+    ///
+    /// A folding is only found when the other form leads to the same entry on the disk.
+    pub fn from_entries(index: &TreeIndex) -> NameFolding {
+        let mut folding = NameFolding::default();
+
+        for path in index.entries.keys() {
+            let disk = index.absolute(path);
+            let Some(name) = disk.file_name().and_then(OsStr::to_str) else {
+                continue;
+            };
+            let same =
+                |other: String| other != name && same_entry(&disk, &disk.with_file_name(other));
+
+            folding.case = folding.case || same(swap_case(name));
+            folding.normalization =
+                folding.normalization || same(name.nfd().collect()) || same(name.nfc().collect());
+            if folding.case && folding.normalization {
+                break;
+            }
+        }
+        folding
+    }
+
+    /// Checks whether different names can point to the same entry.
+    pub fn is_folding(self) -> bool {
+        self.case || self.normalization || self.target.is_some()
+    }
+
+    /// Gives the form of a name that is the same for every name the file system takes as it.
+    ///
+    /// Names that are not valid UTF-8 are kept as they are.
+    pub fn fold(self, name: &OsStr) -> OsString {
+        let encoded = self.target.map(|_| encode_name(name));
+        let name = encoded.as_deref().unwrap_or(name);
+        let Some(name) = name.to_str() else {
+            return name.to_os_string();
+        };
+
+        let name = match self.case {
+            true => name.to_lowercase(),
+            false => name.to_string(),
+        };
+        match self.normalization {
+            true => name.nfc().collect::<String>().into(),
+            false => name.into(),
+        }
+    }
+}
+
+/// Gives the name with every upper case letter in lower case and the other way round.
+fn swap_case(name: &str) -> String {
+    name.chars()
+        .flat_map(|letter| match letter.is_uppercase() {
+            true => letter.to_lowercase().collect::<Vec<_>>(),
+            false => letter.to_uppercase().collect::<Vec<_>>(),
+        })
+        .collect()
+}
+
+/// Checks whether two paths lead to the same entry on the disk.
+fn same_entry(first: &Path, second: &Path) -> bool {
+    match (symlink_metadata(first), symlink_metadata(second)) {
+        (Ok(first), Ok(second)) => first.dev() == second.dev() && first.ino() == second.ino(),
+        _ => false,
+    }
+}
+
+/// Gives the way two colliding names differ, for the report.
+pub fn collision_reason(first: &Path, second: &Path) -> &'static str {
+    let same = |folding: NameFolding| {
+        first.file_name().map(|name| folding.fold(name))
+            == second.file_name().map(|name| folding.fold(name))
+    };
+
+    let mapped = first
+        .file_name()
+        .zip(second.file_name())
+        .is_some_and(|(first, second)| encode_name(first) == encode_name(second));
+
+    if mapped {
+        "characters the target file system rejects"
+    } else if same(NameFolding {
+        normalization: true,
+        ..Default::default()
+    }) {
+        "Unicode normalization"
+    } else if same(NameFolding {
+        case: true,
+        ..Default::default()
+    }) {
+        "case"
+    } else {
+        "case and Unicode normalization"
+    }
+}
+
+/// Prints one line for every colliding pair with the way its names differ.
+pub fn print_collisions(index: &TreeIndex, collisions: &[(PathBuf, PathBuf)]) {
+    for (first, second) in collisions {
+        eprintln!(
+            "[COLLISION]: {} and {} differ only by {}",
+            escaped(&index.absolute(first)),
+            escaped(&index.absolute(second)),
+            collision_reason(first, second)
+        );
+    }
+}
+
+/// Finds the entries of an index that would become one entry on a folding file system.
+///
+/// Takes:
+/// - Index of the tree that is synced
+/// - Folding of the file system it is synced to
+///
+/// Returns:
+/// - Every colliding pair of relative paths, the first path of a group with each other one
+///
+/// Only entries in the same directory are compared, so when two directories collide
+/// their contents are not reported again.
+pub fn name_collisions(index: &TreeIndex, folding: NameFolding) -> Vec<(PathBuf, PathBuf)> {
+    if !folding.is_folding() {
+        return Vec::new();
+    }
+
+    let mut groups: BTreeMap<(&Path, OsString), Vec<&PathBuf>> = BTreeMap::new();
+    for path in index.entries.keys() {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        groups
+            .entry((parent, folding.fold(name)))
+            .or_default()
+            .push(path);
+    }
+
+    groups
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .flat_map(|paths| {
+            let first = paths[0];
+            paths[1..]
+                .iter()
+                .map(move |path| (first.clone(), (*path).clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Implementation for finding the source paths that collide in the destination.
+impl SyncData {
+    /// Finds the source paths that would become one entry in the destination.
+    ///
+    /// Takes:
+    /// - Index of the source and destination
+    ///
+    /// Returns:
+    /// - Every colliding pair of relative source paths, empty when the destination tells
+    ///   every name apart
+    /// - Error if the probe can not be created in the destination
+    ///
+    /// A dry run writes nothing, so the folding is inferred from the destination entries
+    /// instead of probed. The target file systems tell no case apart, whatever is found.
+    pub fn dest_collisions(&self, index: &SyncIndex) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut folding = match self.dry_run {
+            true => NameFolding::from_entries(&index.destination),
+            false => NameFolding::of(&self.destination)?,
+        };
+        if let Some(target) = self.target_fs {
+            folding.case = true;
+            folding.target = Some(target);
+        }
+        Ok(name_collisions(&index.source, folding))
+    }
+}
@@ -0,0 +1,142 @@
+use super::{
+    sparse::{copy_holes, is_sparse, write_sparse},
+    sync::SyncData,
+};
+use crate::error::{Error, Result};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Size in bytes above which modified files are updated in place with a delta by default.
+pub const DEFAULT_DELTA_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+const MIN_BLOCK_SIZE: usize = 2 * 1024;
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+
+/// Gives the block size for a destination file, the square root of its size like rsync.
+fn block_size(len: u64) -> usize {
+    ((len as f64).sqrt() as usize).clamp(MIN_BLOCK_SIZE, MAX_BLOCK_SIZE)
+}
+
+/// Fills the buffer as far as the file goes and gives the number of bytes read.
+fn read_block(file: &mut File, path: &Path, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]).map_err(Error::io(path))? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+/// Writes data at an offset, leaving holes for the runs of zeros when the source is sparse.
+fn write_at(file: &mut File, path: &Path, offset: u64, data: &[u8], sparse: bool) -> Result<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    if sparse {
+        return write_sparse(file, path, offset, data);
+    }
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.write_all(data))
+        .map_err(Error::io(path))
+}
+
+/// Implementation of the block level delta updates.
+impl SyncData {
+    /// Gives the size above which modified files are updated with a delta.
+    pub fn delta_threshold(&self) -> u64 {
+        self.delta_threshold.unwrap_or(DEFAULT_DELTA_THRESHOLD)
+    }
+
+    /// Checks whether a modified file of the given size is updated with a delta.
+    ///
+    /// Only the in-place updates use one, a file that is written to a temporary sibling
+    /// is copied whole.
+    pub fn uses_delta(&self, size: u64) -> bool {
+        self.inplace && !self.whole_file && size > self.delta_threshold()
+    }
+
+    /// Updates the destination file in place with only the blocks that changed.
+    ///
+    /// Takes:
+    /// - Source file with the new content
+    /// - Destination file with the old content
+    ///
+    /// Returns:
+    /// - Number of bytes that are left as they are in the destination
+    /// - Error if any of the files can not be read or written
+    ///
+    /// Both files are read block by block, a block of the square root of the destination
+    /// size. A source block that is the same as the destination block at the same offset is
+    /// not written, the others are written over the destination, which is truncated to the
+    /// source size at the end. Data that moved to another offset is written again, since an
+    /// in-place update has to write it at its new offset anyway.
+    ///
+    /// When the source is sparse, the runs of zeros are left as holes in the destination
+    /// instead of being written, and the holes of the source are punched into the
+    /// blocks that matched.
+    ///
+    /// Unlike a whole file copy the destination is changed in place, so a reader can see
+    /// a partly updated file while it runs, and a failure leaves it partly updated.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::PathBuf;
+    ///
+    /// let sync = SyncData::default();
+    ///
+    /// let saved = sync
+    ///     .delta_update(&PathBuf::from("disk.img"), &PathBuf::from("backup/disk.img"))
+    ///     .expect("[ERROR]: failed to update the file");
+    /// println!("{} bytes saved", saved);
+    /// ```
+    pub fn delta_update(&self, src_path: &Path, dest_path: &Path) -> Result<u64> {
+        let mut src = File::open(src_path).map_err(Error::io(src_path))?;
+        let mut dest = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(dest_path)
+            .map_err(Error::io(dest_path))?;
+
+        let src_meta = src.metadata().map_err(Error::io(src_path))?;
+        let src_len = src_meta.len();
+        let sparse = is_sparse(&src_meta);
+        let dest_len = dest.metadata().map_err(Error::io(dest_path))?.len();
+        let block = block_size(dest_len);
+
+        let mut src_block = vec![0u8; block];
+        let mut dest_block = vec![0u8; block];
+        let mut offset: u64 = 0;
+        let mut saved = 0;
+
+        loop {
+            let read = read_block(&mut src, src_path, &mut src_block)?;
+            if read == 0 {
+                break;
+            }
+
+            // The destination is read from the same offset, which a write before may have moved
+            dest.seek(SeekFrom::Start(offset))
+                .map_err(Error::io(dest_path))?;
+            let old = read_block(&mut dest, dest_path, &mut dest_block[..read])?;
+            if old == read && src_block[..read] == dest_block[..read] {
+                saved += read as u64;
+            } else {
+                write_at(&mut dest, dest_path, offset, &src_block[..read], sparse)?;
+            }
+            offset += read as u64;
+        }
+
+        dest.set_len(src_len).map_err(Error::io(dest_path))?;
+        if sparse {
+            copy_holes(&src, src_path, &mut dest, dest_path, src_len)?;
+        }
+        Ok(saved)
+    }
+}
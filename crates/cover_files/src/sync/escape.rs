@@ -0,0 +1,94 @@
+use std::{
+    ffi::OsString,
+    fmt,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+/// Shows a path in the logs and output without losing any of its bytes.
+///
+/// Printable UTF-8 is written as it is. A backslash is written as `\\`, and every byte
+/// of a control character or of a name that is not valid UTF-8 as `\xNN`, so
+/// `unescape` gives the same path back.
+pub struct Escaped<'a>(&'a Path);
+
+/// Wraps a path to print it escaped, like `Path::display` but round-trippable.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cover_files::sync::escape::escaped;
+/// use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::Path};
+///
+/// let path = Path::new(OsStr::from_bytes(b"docs/caf\xe9.txt"));
+/// assert_eq!(escaped(path).to_string(), "docs/caf\\xe9.txt");
+/// ```
+pub fn escaped(path: &Path) -> Escaped<'_> {
+    Escaped(path)
+}
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |f: &mut fmt::Formatter<'_>, bytes: &[u8]| {
+            bytes
+                .iter()
+                .try_for_each(|byte| write!(f, "\\x{:02x}", byte))
+        };
+
+        for chunk in self.0.as_os_str().as_bytes().utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match c {
+                    '\\' => f.write_str("\\\\")?,
+                    c if c.is_control() => hex(f, c.encode_utf8(&mut [0; 4]).as_bytes())?,
+                    c => write!(f, "{}", c)?,
+                }
+            }
+            hex(f, chunk.invalid())?;
+        }
+        Ok(())
+    }
+}
+
+/// Gives back the path of an escaped text.
+///
+/// Takes:
+/// - Text that is printed by `escaped`
+///
+/// Returns:
+/// - Path with the same bytes, or `None` when an escape is not complete
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cover_files::sync::escape::{escaped, unescape};
+/// use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::Path};
+///
+/// let path = Path::new(OsStr::from_bytes(b"a\\b\n\xff"));
+/// assert_eq!(unescape(&escaped(path).to_string()).as_deref(), Some(path));
+/// ```
+pub fn unescape(text: &str) -> Option<PathBuf> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        match rest {
+            [b'\\', tail @ ..] => {
+                bytes.push(b'\\');
+                rest = tail;
+            }
+            [b'x', high, low, tail @ ..] if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                let digits = [*high, *low];
+                let digits = std::str::from_utf8(&digits).ok()?;
+                bytes.push(u8::from_str_radix(digits, 16).ok()?);
+                rest = tail;
+            }
+            _ => return None,
+        }
+    }
+    Some(PathBuf::from(OsString::from_vec(bytes)))
+}
@@ -0,0 +1,233 @@
+use super::{
+    gitignore::{GIT_DIR, GitIgnore},
+    sync::SyncData,
+};
+use crate::error::{Error, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file that holds the exclude patterns of a source directory.
+pub const IGNORE_FILE: &str = ".coverignore";
+
+/// A single include or exclude pattern given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterRule {
+    /// Keep the matching paths, even when a later rule excludes them
+    Include(String),
+    /// Leave the matching paths alone on both sides
+    Exclude(String),
+}
+
+/// Compiled pattern along with the directory it is relative to.
+#[derive(Debug, Clone)]
+struct Rule {
+    matcher: GlobMatcher,
+    include: bool,
+    dir_only: bool,
+    /// Directory of the ignore file the rule is read from, empty for the command line rules
+    base: PathBuf,
+}
+
+impl Rule {
+    /// Compiles a pattern with the rsync rules.
+    ///
+    /// A pattern that starts with `/` is anchored to the base directory, any other one
+    /// matches at any depth. A trailing `/` matches only directories, `*` stops at `/`
+    /// and `**` goes through it.
+    fn new(pattern: &str, include: bool, base: &Path) -> Result<Self> {
+        let dir_only = pattern.ends_with('/');
+        let trimmed = pattern.trim_end_matches('/');
+
+        let glob = match trimmed.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None => format!("**/{}", trimmed),
+        };
+
+        let matcher = GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| Error::InvalidPattern {
+                pattern: pattern.to_string(),
+                reason: e.kind().to_string(),
+            })?
+            .compile_matcher();
+
+        Ok(Rule {
+            matcher,
+            include,
+            dir_only,
+            base: base.to_path_buf(),
+        })
+    }
+
+    fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        match relative.strip_prefix(&self.base) {
+            Ok(rest) => !rest.as_os_str().is_empty() && self.matcher.is_match(rest),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Parses the lines of an ignore file.
+///
+/// Empty lines and lines that start with `#` are skipped, a line that starts with `!`
+/// includes the paths. The lines keep their order, so the first one that matches decides.
+fn parse_ignore_file(content: &str, base: &Path) -> Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let rule = match line.strip_prefix('!') {
+            Some(pattern) => Rule::new(pattern, true, base)?,
+            None => Rule::new(line, false, base)?,
+        };
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Decides which paths are left out of the sync.
+///
+/// The command line rules are checked first, in the order they are given. Then the
+/// ignore files are checked from the directory of the path up to the source root, so
+/// the rules of a sub-directory come before the ones of its parents. The first rule
+/// that matches decides, a path that matches no rule is synced.
+///
+/// After them the `.git` directory is left out when it is asked for, and the git ignore
+/// rules are applied when `respect_gitignore` is set.
+///
+/// The ignore files are always read from the source, so the same paths are left out
+/// of the source and destination.
+#[derive(Debug)]
+pub struct PathFilter {
+    rules: Vec<Rule>,
+    source: PathBuf,
+    dir_rules: RefCell<HashMap<PathBuf, Vec<Rule>>>,
+    exclude_git_dir: bool,
+    git: Option<GitIgnore>,
+}
+
+impl PathFilter {
+    /// Loads the rules of the ignore file in a directory relative to the source, once.
+    fn rules_in(&self, dir: &Path) -> Result<Vec<Rule>> {
+        if let Some(rules) = self.dir_rules.borrow().get(dir) {
+            return Ok(rules.clone());
+        }
+
+        let path = self.source.join(dir).join(IGNORE_FILE);
+        let rules = if path.is_file() {
+            let content = read_to_string(&path).map_err(Error::io(&path))?;
+            parse_ignore_file(&content, dir)?
+        } else {
+            Vec::new()
+        };
+
+        self.dir_rules
+            .borrow_mut()
+            .insert(dir.to_path_buf(), rules.clone());
+        Ok(rules)
+    }
+
+    /// Checks whether a path relative to the source or destination is left out.
+    ///
+    /// Takes:
+    /// - Relative path
+    /// - Whether the path is a directory
+    ///
+    /// Returns:
+    /// - Boolean to show the path is excluded
+    /// - Error if an ignore file can not be read or has an invalid pattern
+    pub fn is_excluded(&self, relative: &Path, is_dir: bool) -> Result<bool> {
+        if let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(relative, is_dir))
+        {
+            return Ok(!rule.include);
+        }
+
+        for dir in relative.ancestors().skip(1) {
+            let rules = self.rules_in(dir)?;
+            if let Some(rule) = rules.iter().find(|rule| rule.matches(relative, is_dir)) {
+                return Ok(!rule.include);
+            }
+        }
+
+        if self.exclude_git_dir && relative.file_name() == Some(GIT_DIR.as_ref()) {
+            return Ok(true);
+        }
+        match &self.git {
+            Some(git) => git.is_ignored(relative, is_dir),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Implementation for building the filter of the paths.
+impl SyncData {
+    /// Compiles the include and exclude rules of the sync.
+    ///
+    /// Returns:
+    /// - Filter that is used while scanning both sides
+    /// - Error if a pattern is invalid or the git ignore files can not be loaded
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::exclude::FilterRule;
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::{Path, PathBuf};
+    ///
+    /// let sync = SyncData {
+    ///     source: PathBuf::from("source_directory"),
+    ///     destination: PathBuf::from("destination_directory"),
+    ///     changed_only: true,
+    ///     filters: vec![
+    ///         FilterRule::Include("keep.tmp".to_string()),
+    ///         FilterRule::Exclude("*.tmp".to_string()),
+    ///         FilterRule::Exclude("node_modules/".to_string()),
+    ///     ],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let filter = sync.path_filter().expect("[ERROR]: failed to compile the patterns");
+    /// assert!(filter.is_excluded(Path::new("cache/old.tmp"), false).unwrap());
+    /// assert!(!filter.is_excluded(Path::new("keep.tmp"), false).unwrap());
+    /// ```
+    pub fn path_filter(&self) -> Result<PathFilter> {
+        let rules = self
+            .filters
+            .iter()
+            .map(|filter| match filter {
+                FilterRule::Include(pattern) => Rule::new(pattern, true, Path::new("")),
+                FilterRule::Exclude(pattern) => Rule::new(pattern, false, Path::new("")),
+            })
+            .collect::<Result<Vec<Rule>>>()?;
+
+        let git = if self.respect_gitignore && self.source.is_dir() {
+            Some(GitIgnore::new(&self.source)?)
+        } else {
+            None
+        };
+
+        Ok(PathFilter {
+            rules,
+            source: self.source.clone(),
+            dir_rules: RefCell::new(HashMap::new()),
+            exclude_git_dir: self.exclude_git_dir,
+            git,
+        })
+    }
+}
@@ -1,5 +1,5 @@
 use super::sync::SyncData;
-use std::{collections::HashSet, path::PathBuf};
+use std::os::unix::ffi::OsStrExt;
 
 /// Implemenation of the helper methods used in other methods.
 impl SyncData {
@@ -23,13 +23,20 @@ impl SyncData {
     ///     delete: false,
     ///     dry_run: false,
     ///     verbose: false,
+    ///     ..Default::default()
     /// };
     ///
     /// assert!(sync.src_dest_dir_present(), "[ERROR]: source or destination not detected");
     /// ```
+    #[allow(clippy::needless_bool, clippy::needless_return)]
     pub fn src_dest_dir_present(&self) -> bool {
-        let src_not_empty = !self.source.to_string_lossy().trim().is_empty();
-        let dest_not_empty = !self.destination.to_string_lossy().trim().is_empty();
+        let src_not_empty = !self.source.as_os_str().as_bytes().trim_ascii().is_empty();
+        let dest_not_empty = !self
+            .destination
+            .as_os_str()
+            .as_bytes()
+            .trim_ascii()
+            .is_empty();
 
         if !self.source.exists() && !self.destination.exists() {
             return false;
@@ -66,13 +73,19 @@ impl SyncData {
     ///     delete: false,
     ///     dry_run: false,
     ///     verbose: false,
+    ///     ..Default::default()
     /// };
     ///
     /// assert!(sync.single_command_selected(), "[ERROR]: expected one command, but multiple are reported");
     /// ```
     pub fn single_command_selected(&mut self) -> bool {
-        let source_contains = !self.source.to_string_lossy().trim().is_empty();
-        let destination_contains = !self.destination.to_string_lossy().trim().is_empty();
+        let source_contains = !self.source.as_os_str().as_bytes().trim_ascii().is_empty();
+        let destination_contains = !self
+            .destination
+            .as_os_str()
+            .as_bytes()
+            .trim_ascii()
+            .is_empty();
 
         if !self.source.is_dir() && !self.destination.is_dir() {
             return false;
@@ -95,116 +108,10 @@ impl SyncData {
         if self.verbose {
             count += 1;
         }
-
-        count == 1
-    }
-
-    /// Gets the file and directory names for checking the duplication.
-    ///
-    /// Returns:
-    /// - Directories list
-    /// - Files list
-    ///
-    /// After listing the files and directories, splits the full path of them and takes only the last name to return.
-    ///
-    /// # Example
-    ///
-    /// ```rust,no_run
-    /// use cover_files::sync::sync::SyncData;
-    /// use std::path::PathBuf;
-    ///
-    /// let sync = SyncData {
-    ///     source: PathBuf::from("source_directory"),
-    ///     destination: PathBuf::new(),
-    ///     changed_only: true,
-    ///     delete: false,
-    ///     dry_run: false,
-    ///     verbose: false,
-    /// };
-    ///
-    /// let (dir_list, file_list) = sync.get_file_names();
-    /// assert!(dir_list.len() != 0 && file_list.len() != 0);
-    /// ```
-    pub fn get_file_names(&self) -> (Vec<PathBuf>, Vec<PathBuf>) {
-        let mut dir_list: Vec<PathBuf> = Vec::new();
-        let mut file_list: Vec<PathBuf> = Vec::new();
-
-        let src_dirs = self.list_src_dirs();
-        let src_files = self.list_src_files();
-
-        for entry in src_dirs {
-            if entry == self.source {
-                continue;
-            }
-
-            let mut comp = entry.components();
-            comp.next();
-            let path = comp.as_path();
-
-            let path_data = path.display().to_string();
-            if let Some(dir) = path_data.clone().split("/").last() {
-                dir_list.push(PathBuf::from(dir));
-            }
-        }
-
-        for entry in src_files {
-            if entry == self.source {
-                continue;
-            }
-
-            let mut comp = entry.components();
-            comp.next();
-            let path = comp.as_path();
-
-            let path_data = path.display().to_string();
-            if let Some(file) = path_data.clone().split("/").last() {
-                file_list.push(PathBuf::from(file));
-            }
-        }
-
-        (dir_list, file_list)
-    }
-
-    /// It prevents the duplication of files and directories to be inserted.
-    ///
-    /// Returns:
-    /// - Boolean to check if the files or directories has duplicates.
-    ///
-    /// # Example
-    ///
-    /// ```rust,no_run
-    /// use cover_files::sync::sync::SyncData;
-    /// use std::path::PathBuf;
-    ///
-    /// let sync = SyncData {
-    ///     source: PathBuf::from("source_directory"),
-    ///     destination: PathBuf::from("destination_directory"),
-    ///     changed_only: true,
-    ///     delete: false,
-    ///     dry_run: false,
-    ///     verbose: false,
-    /// };
-    ///
-    /// let result = sync.has_duplicates();
-    /// assert!(result == true);
-    /// ```
-    pub fn has_duplicates(&self) -> bool {
-        let mut dir_hash: HashSet<PathBuf> = HashSet::new();
-        let mut file_hash: HashSet<PathBuf> = HashSet::new();
-        let (dir_list, file_list) = self.get_file_names();
-
-        for dir in dir_list {
-            if !dir_hash.insert(dir) {
-                return true;
-            }
-        }
-
-        for file in file_list {
-            if !file_hash.insert(file) {
-                return true;
-            }
+        if self.mirror {
+            count += 1;
         }
 
-        false
+        count == 1
     }
 }
@@ -0,0 +1,145 @@
+use super::escape::escaped;
+use crate::error::{Error, Result};
+use ignore::{
+    Match,
+    gitignore::{Gitignore, GitignoreBuilder},
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Name of the ignore file git reads in every directory of a work tree.
+pub const GITIGNORE_FILE: &str = ".gitignore";
+
+/// Name of the directory, or of the file in a linked work tree, that holds the git data.
+pub const GIT_DIR: &str = ".git";
+
+/// Turns a partly invalid ignore file into the error of the file.
+fn ignore_error(path: &Path, error: ignore::Error) -> Error {
+    Error::InvalidPattern {
+        pattern: escaped(path).to_string(),
+        reason: error.to_string(),
+    }
+}
+
+/// Applies the ignore rules of git to the paths of a source tree.
+///
+/// Works like `git status`: the `.gitignore` files are checked from the directory of
+/// the path up to the top of the work tree, so a nested file overrides its parents.
+/// Inside a file the last line that matches decides and a line that starts with `!`
+/// brings a path back. Then `.git/info/exclude` of the repository and the global
+/// excludes file of `core.excludesFile` are checked.
+///
+/// When the source is not inside a git work tree the `.gitignore` files are read up
+/// to the source and only the global excludes file is used besides them.
+#[derive(Debug)]
+pub struct GitIgnore {
+    /// Canonical source, the relative paths are joined to it
+    source: PathBuf,
+    /// Top of the work tree, or the source when it is not in one
+    top: PathBuf,
+    info_exclude: Gitignore,
+    global: Gitignore,
+    dir_ignores: RefCell<HashMap<PathBuf, Gitignore>>,
+}
+
+impl GitIgnore {
+    /// Finds the work tree of the source and loads its repository wide ignore files.
+    ///
+    /// Takes:
+    /// - Source directory
+    ///
+    /// Returns:
+    /// - Git ignore rules of the source
+    /// - Error if the source can not be resolved or an ignore file is invalid
+    pub fn new(source: &Path) -> Result<Self> {
+        let source = source.canonicalize().map_err(Error::io(source))?;
+        let repository = source
+            .ancestors()
+            .find(|dir| dir.join(GIT_DIR).exists())
+            .map(Path::to_path_buf);
+        let top = repository.clone().unwrap_or_else(|| source.clone());
+
+        let info_exclude = match &repository {
+            Some(repository) => {
+                let path = repository.join(GIT_DIR).join("info").join("exclude");
+                load(&path, repository)?
+            }
+            None => Gitignore::empty(),
+        };
+
+        let (global, error) = GitignoreBuilder::new(&top).build_global();
+        if let Some(error) = error {
+            return Err(ignore_error(&top, error));
+        }
+
+        Ok(GitIgnore {
+            source,
+            top,
+            info_exclude,
+            global,
+            dir_ignores: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Loads the `.gitignore` file of a directory, once.
+    fn ignore_in(&self, dir: &Path) -> Result<Gitignore> {
+        if let Some(ignore) = self.dir_ignores.borrow().get(dir) {
+            return Ok(ignore.clone());
+        }
+
+        let ignore = load(&dir.join(GITIGNORE_FILE), dir)?;
+        self.dir_ignores
+            .borrow_mut()
+            .insert(dir.to_path_buf(), ignore.clone());
+        Ok(ignore)
+    }
+
+    /// Checks whether git ignores a path relative to the source.
+    ///
+    /// Takes:
+    /// - Relative path
+    /// - Whether the path is a directory
+    ///
+    /// Returns:
+    /// - Boolean to show the path is ignored
+    /// - Error if an ignore file can not be read or has an invalid pattern
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> Result<bool> {
+        let path = self.source.join(relative);
+
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(&self.top) {
+                break;
+            }
+            match self.ignore_in(dir)?.matched(&path, is_dir) {
+                Match::Ignore(_) => return Ok(true),
+                Match::Whitelist(_) => return Ok(false),
+                Match::None => {}
+            }
+        }
+
+        for ignore in [&self.info_exclude, &self.global] {
+            match ignore.matched(&path, is_dir) {
+                Match::Ignore(_) => return Ok(true),
+                Match::Whitelist(_) => return Ok(false),
+                Match::None => {}
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Reads an ignore file whose patterns are relative to the given directory.
+fn load(path: &Path, base: &Path) -> Result<Gitignore> {
+    if !path.is_file() {
+        return Ok(Gitignore::empty());
+    }
+
+    let mut builder = GitignoreBuilder::new(base);
+    if let Some(error) = builder.add(path) {
+        return Err(ignore_error(path, error));
+    }
+    builder.build().map_err(|e| ignore_error(path, e))
+}
@@ -0,0 +1,128 @@
+use super::{
+    escape::escaped,
+    index::TreeIndex,
+    plan::{OperationKind, SyncPlan},
+    sync::SyncData,
+};
+use crate::error::{Error, Result};
+use std::io::{self, IsTerminal, Write};
+
+/// Gives the number of destination entries a plan removes.
+///
+/// A replaced directory counts with every indexed entry in it.
+pub fn planned_removals(plan: &SyncPlan, dest: &TreeIndex) -> usize {
+    let removed = plan
+        .iter()
+        .filter(|op| matches!(op.kind, OperationKind::Delete | OperationKind::Rmdir))
+        .count();
+    removed + replaced_entries(plan, dest)
+}
+
+/// Gives the number of destination entries that are removed to make way for a source entry of another type.
+pub fn replaced_entries(plan: &SyncPlan, dest: &TreeIndex) -> usize {
+    plan.iter()
+        .filter(|op| op.kind == OperationKind::Replace)
+        .map(|op| {
+            dest.entries
+                .keys()
+                .filter(|path| path.starts_with(&op.path))
+                .count()
+        })
+        .sum()
+}
+
+/// Gives the number of entries that are removed when the whole destination index is removed.
+///
+/// The directories holding excluded paths are kept, like `remove_indexed_dest_files` does.
+pub fn indexed_removals(dest: &TreeIndex) -> usize {
+    let kept = dest.dirs().filter(|dir| dest.holds_excluded(dir)).count();
+    dest.entries.len() - kept
+}
+
+/// Implementation for the checks that run before any destination entry is removed.
+impl SyncData {
+    /// Stops the sync when it would remove more destination entries than the limits allow.
+    ///
+    /// Takes:
+    /// - Number of destination entries that would be removed
+    /// - Number of entries in the destination index
+    ///
+    /// Returns:
+    /// - Error if the count is above `max_delete`, or its share of the destination is
+    ///   above `max_delete_percent`
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::PathBuf;
+    ///
+    /// let sync = SyncData {
+    ///     source: PathBuf::from("source_directory"),
+    ///     destination: PathBuf::from("destination_directory"),
+    ///     mirror: true,
+    ///     max_delete: Some(100),
+    ///     max_delete_percent: Some(10.0),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert!(sync.check_delete_limits(5, 1000).is_ok());
+    /// assert!(sync.check_delete_limits(200, 1000).is_err());
+    /// assert!(sync.check_delete_limits(20, 100).is_err());
+    /// ```
+    pub fn check_delete_limits(&self, count: usize, total: usize) -> Result<()> {
+        if let Some(limit) = self.max_delete
+            && count > limit
+        {
+            return Err(Error::MaxDelete { count, limit });
+        }
+        if let Some(percent) = self.max_delete_percent
+            && count as f64 * 100.0 > percent * total as f64
+        {
+            return Err(Error::MaxDeletePercent {
+                count,
+                total,
+                percent,
+            });
+        }
+        Ok(())
+    }
+
+    /// Asks on the terminal whether the destination entries may be removed.
+    ///
+    /// Takes:
+    /// - Number of destination entries that would be removed
+    ///
+    /// Returns:
+    /// - Error if the answer is not `y` or `yes`
+    ///
+    /// Only asks when `confirm_removals` is set, something is removed and the standard
+    /// input is a terminal, so scripts and pipes are never blocked.
+    pub fn ask_to_remove(&self, count: usize) -> Result<()> {
+        if !self.confirm_removals || count == 0 || !io::stdin().is_terminal() {
+            return Ok(());
+        }
+
+        eprint!(
+            "[CONFIRM]: remove {} entr(ies) from {}? [y/N] ",
+            count,
+            escaped(&self.destination)
+        );
+        io::stderr().flush().ok();
+
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err() {
+            return Err(Error::NotConfirmed);
+        }
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" => Ok(()),
+            _ => Err(Error::NotConfirmed),
+        }
+    }
+
+    /// Runs the limits and then the confirmation, before anything of the sync is applied.
+    pub(crate) fn guard_removals(&self, count: usize, total: usize) -> Result<()> {
+        self.check_delete_limits(count, total)?;
+        self.ask_to_remove(count)
+    }
+}
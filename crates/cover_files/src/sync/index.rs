@@ -0,0 +1,506 @@
+use super::{
+    escape::escaped,
+    links::{LinkPolicy, is_link_loop},
+    select::now,
+    special::special_kind,
+    sync::SyncData,
+    target::decode_path,
+};
+use crate::error::{Error, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{Metadata, metadata, read_link, symlink_metadata},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+use walkdir::WalkDir;
+
+/// Points to the kind of an entry in the tree index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// Regular file
+    File,
+    /// Directory
+    Dir,
+    /// Symbolic link that is kept as a link, its target is in the links of the index
+    Symlink,
+    /// FIFO, socket or device node, its mode and device number are in the specials of the index
+    Special,
+}
+
+/// Metadata of a single entry that is kept in the tree index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub kind: EntryKind,
+    /// Size in bytes, zero for directories
+    pub size: u64,
+    /// Modification time since the unix epoch
+    pub modified: Duration,
+}
+
+impl IndexEntry {
+    fn from_metadata(meta: &Metadata) -> Option<Self> {
+        let kind = if meta.is_file() {
+            EntryKind::File
+        } else if meta.is_dir() {
+            EntryKind::Dir
+        } else if special_kind(&meta.file_type()).is_some() {
+            EntryKind::Special
+        } else {
+            return None;
+        };
+
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        Some(IndexEntry {
+            kind,
+            size: if kind == EntryKind::File {
+                meta.len()
+            } else {
+                0
+            },
+            modified,
+        })
+    }
+
+    fn from_link(meta: &Metadata) -> Self {
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        IndexEntry {
+            kind: EntryKind::Symlink,
+            size: 0,
+            modified,
+        }
+    }
+}
+
+/// Checks whether a walk error comes from a link that can not be followed.
+///
+/// Returns the path of the link and the reason it is skipped.
+fn link_problem(error: &walkdir::Error) -> Option<(PathBuf, String)> {
+    let path = error.path()?.to_path_buf();
+    if let Some(ancestor) = error.loop_ancestor() {
+        let reason = format!("symlink loop back to '{}'", escaped(ancestor));
+        return Some((path, reason));
+    }
+    if !symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_symlink()) {
+        return None;
+    }
+
+    let reason = if is_link_loop(&path) {
+        "symlink loop".to_string()
+    } else {
+        "broken symlink".to_string()
+    };
+    Some((path, reason))
+}
+
+/// Holds every entry under one directory, keyed by the path relative to it.
+///
+/// The directory itself is not part of the entries. Parents are always ordered
+/// before their children.
+#[derive(Debug, Default)]
+pub struct TreeIndex {
+    pub root: PathBuf,
+    pub entries: BTreeMap<PathBuf, IndexEntry>,
+    /// Paths that are left out by the include and exclude rules, their contents are not scanned
+    pub excluded: BTreeSet<PathBuf>,
+    /// Files that are left out by the size, age and type filters, they are also in `excluded`
+    pub filtered: BTreeMap<PathBuf, IndexEntry>,
+    /// Targets of the links that are kept as links
+    pub links: BTreeMap<PathBuf, PathBuf>,
+    /// Paths that could not be indexed with the reason, like links that run into a loop
+    pub skipped: Vec<(PathBuf, String)>,
+    /// Device and inode of the files with more than one link, when hard links are kept
+    pub inodes: BTreeMap<PathBuf, (u64, u64)>,
+    /// Mode and device number of the FIFOs, sockets and device nodes that are recreated
+    pub specials: BTreeMap<PathBuf, (u32, u64)>,
+    /// Entries on another file system than the root, they are also in `excluded`
+    pub mount_points: BTreeSet<PathBuf>,
+    /// Relative paths on the disk of the destination entries whose names are mapped for the target file system
+    pub names: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl TreeIndex {
+    /// Gives the entry of a relative path.
+    pub fn get(&self, path: &Path) -> Option<&IndexEntry> {
+        self.entries.get(path)
+    }
+
+    /// Checks whether the relative path is present.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    /// Checks whether the relative path is a file.
+    pub fn is_file(&self, path: &Path) -> bool {
+        self.get(path)
+            .is_some_and(|entry| entry.kind == EntryKind::File)
+    }
+
+    /// Checks whether the relative path is a directory.
+    pub fn is_dir(&self, path: &Path) -> bool {
+        self.get(path)
+            .is_some_and(|entry| entry.kind == EntryKind::Dir)
+    }
+
+    /// Checks whether the relative path is a link that is kept as a link.
+    pub fn is_link(&self, path: &Path) -> bool {
+        self.links.contains_key(path)
+    }
+
+    /// Gives the target of a link that is kept as a link.
+    pub fn link_target(&self, path: &Path) -> Option<&PathBuf> {
+        self.links.get(path)
+    }
+
+    /// Gives the size of the relative path, zero when it is not indexed.
+    pub fn size_of(&self, path: &Path) -> u64 {
+        self.get(path).map(|entry| entry.size).unwrap_or(0)
+    }
+
+    /// Checks whether a directory holds an excluded path, so it must not be removed.
+    pub fn holds_excluded(&self, dir: &Path) -> bool {
+        self.excluded
+            .range(dir.to_path_buf()..)
+            .next()
+            .is_some_and(|path| path.starts_with(dir) && path != dir)
+    }
+
+    /// Iterates over the relative paths of the files.
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.kind == EntryKind::File)
+            .map(|(path, _)| path)
+    }
+
+    /// Iterates over the relative paths of the directories.
+    pub fn dirs(&self) -> impl Iterator<Item = &PathBuf> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.kind == EntryKind::Dir)
+            .map(|(path, _)| path)
+    }
+
+    /// Checks whether two relative paths are hard links to the same file.
+    pub fn same_file(&self, first: &Path, second: &Path) -> bool {
+        match (self.inodes.get(first), self.inodes.get(second)) {
+            (Some(first), Some(second)) => first == second,
+            _ => false,
+        }
+    }
+
+    /// Groups the files that are hard links to the same file.
+    ///
+    /// Every group has at least two paths in their order, and the groups are ordered by
+    /// their first path. The first path is the one whose content is copied.
+    pub fn link_groups(&self) -> Vec<Vec<&PathBuf>> {
+        let mut groups: BTreeMap<(u64, u64), Vec<&PathBuf>> = BTreeMap::new();
+        for (path, inode) in &self.inodes {
+            groups.entry(*inode).or_default().push(path);
+        }
+
+        let mut groups: Vec<Vec<&PathBuf>> = groups
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    /// Checks whether the relative path is a FIFO, socket or device node that is recreated.
+    pub fn is_special(&self, path: &Path) -> bool {
+        self.specials.contains_key(path)
+    }
+
+    /// Iterates over the relative paths of the FIFOs, sockets and device nodes that are recreated.
+    pub fn specials(&self) -> impl Iterator<Item = &PathBuf> {
+        self.specials.keys()
+    }
+
+    /// Iterates over the relative paths of the links that are kept as links.
+    pub fn links(&self) -> impl Iterator<Item = &PathBuf> {
+        self.links.keys()
+    }
+
+    /// Joins a relative path with the root of the index, with the name it has on the disk.
+    pub fn absolute(&self, path: &Path) -> PathBuf {
+        self.root
+            .join(self.names.get(path).map_or(path, PathBuf::as_path))
+    }
+}
+
+/// Holds the scan of both sides of the sync.
+#[derive(Debug, Default)]
+pub struct SyncIndex {
+    pub source: TreeIndex,
+    pub destination: TreeIndex,
+    /// Entries that could not be scanned while continuing on error
+    pub failures: Vec<Error>,
+}
+
+/// Makes the source decide the size, age and type filters for the files on both sides.
+///
+/// A file that is synced or left out in the source is treated the same way in the
+/// destination, so an old copy that passes the filters is not deleted and a copy that
+/// fails them is still updated. Files that are only in the destination keep their own result.
+fn select_like_source(source: &TreeIndex, destination: &mut TreeIndex) {
+    let restored: Vec<PathBuf> = destination
+        .filtered
+        .keys()
+        .filter(|path| source.is_file(path))
+        .cloned()
+        .collect();
+    for path in restored {
+        if let Some(entry) = destination.filtered.remove(&path) {
+            destination.excluded.remove(&path);
+            destination.entries.insert(path, entry);
+        }
+    }
+
+    let left_out: Vec<PathBuf> = source
+        .filtered
+        .keys()
+        .filter(|path| destination.is_file(path))
+        .cloned()
+        .collect();
+    for path in left_out {
+        if let Some(entry) = destination.entries.remove(&path) {
+            destination.excluded.insert(path.clone());
+            destination.filtered.insert(path, entry);
+        }
+    }
+}
+
+/// Implementation for scanning the source and destination into an index.
+impl SyncData {
+    /// Scans the source and destination once and keeps their entries in memory.
+    ///
+    /// Returns:
+    /// - Index of both sides with the kind, size and modification time of every entry
+    /// - Error if a directory can not be read, unless the sync continues on error
+    ///
+    /// Every detector, log and action that gets the index reuses it instead of walking
+    /// the directories again.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::PathBuf;
+    ///
+    /// let sync = SyncData {
+    ///     source: PathBuf::from("source_directory"),
+    ///     destination: PathBuf::from("destination_directory"),
+    ///     changed_only: true,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let index = sync.scan().expect("[ERROR]: failed to scan the directories");
+    /// for file in index.source.files() {
+    ///     println!("{}", file.display());
+    /// }
+    /// ```
+    pub fn scan(&self) -> Result<SyncIndex> {
+        let mut failures = Vec::new();
+        let source = self.scan_tree(&self.source, &mut failures)?;
+        let mut destination = self.scan_tree(&self.destination, &mut failures)?;
+        self.match_dest_names(&source, &mut destination);
+        if self.has_file_selection() {
+            select_like_source(&source, &mut destination);
+        }
+
+        Ok(SyncIndex {
+            source,
+            destination,
+            failures,
+        })
+    }
+
+    /// Walks through the given directory once and indexes every file and directory in it.
+    ///
+    /// An entry that can not be read stops the walk, unless the sync continues on error.
+    /// Then it is pushed to the failures and the walk goes on with the rest of the tree.
+    ///
+    /// The paths that are excluded by the include and exclude rules are kept apart from
+    /// the entries, and the contents of an excluded directory are not walked. Files that
+    /// fail the size, age and type filters are kept apart the same way. When the sync stays
+    /// on one file system, the entries on another device are kept apart as mount points.
+    /// With `target_fs`, a destination entry whose name maps back to the key of an entry
+    /// that is already indexed is left out and reported like an entry that can not be read.
+    pub(crate) fn scan_tree(&self, root: &Path, failures: &mut Vec<Error>) -> Result<TreeIndex> {
+        let mut index = TreeIndex {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+
+        if !root.is_dir() {
+            return Err(Error::NotADirectory(root.to_path_buf()));
+        }
+
+        let filter = self.path_filter()?;
+        let now = now();
+        let is_source = root == self.source.as_path();
+        let follow = is_source && self.links == LinkPolicy::Follow;
+        let device = match self.one_file_system {
+            true => Some(metadata(root).map_err(Error::io(root))?.dev()),
+            false => None,
+        };
+        let mut walker = WalkDir::new(root)
+            .min_depth(1)
+            .follow_links(follow)
+            .into_iter();
+        // Names on the disk of the destination entries by the key they are indexed with
+        let mut disk_names: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    // Links that can not be followed are reported instead of failing the walk
+                    if let Some((path, reason)) = link_problem(&e) {
+                        index.skipped.push((path, reason));
+                        continue;
+                    }
+                    self.recover(Err::<(), _>(Error::from(e)), failures)?;
+                    continue;
+                }
+            };
+
+            let relative = entry
+                .path()
+                .strip_prefix(root)
+                .map_err(|_| Error::OutsideRoot {
+                    path: entry.path().to_path_buf(),
+                    root: root.to_path_buf(),
+                })?
+                .to_path_buf();
+            // The mapped names of the destination are indexed as the source names they stand for
+            let relative = match self.target_fs {
+                Some(_) if !is_source => {
+                    let decoded = decode_path(&relative);
+                    // A second name for the same key would hide the first one, so it is reported
+                    if let Some(other) = disk_names.get(&decoded) {
+                        if entry.file_type().is_dir() {
+                            walker.skip_current_dir();
+                        }
+                        let taken = Error::DestNameTaken {
+                            path: entry.path().to_path_buf(),
+                            other: root.join(other),
+                        };
+                        self.recover(Err::<(), _>(taken), failures)?;
+                        continue;
+                    }
+                    disk_names.insert(decoded.clone(), relative.clone());
+                    if decoded != relative {
+                        index.names.insert(decoded.clone(), relative);
+                    }
+                    decoded
+                }
+                _ => relative,
+            };
+
+            // Without following, a link is indexed as it is with its target
+            if entry.file_type().is_symlink() {
+                if self.links == LinkPolicy::Skip {
+                    index.excluded.insert(relative);
+                    continue;
+                }
+                let link = read_link(entry.path())
+                    .map_err(Error::io(entry.path()))
+                    .and_then(|target| {
+                        let meta = entry.metadata().map_err(Error::from)?;
+                        Ok((target, IndexEntry::from_link(&meta)))
+                    });
+                let Some((target, index_entry)) = self.recover(link, failures)? else {
+                    continue;
+                };
+
+                match self.recover(filter.is_excluded(&relative, false), failures)? {
+                    Some(false) => {}
+                    Some(true) => {
+                        index.excluded.insert(relative);
+                        continue;
+                    }
+                    None => continue,
+                }
+
+                let target = if is_source {
+                    self.link_target(&relative, &target)
+                } else {
+                    target
+                };
+                index.links.insert(relative.clone(), target);
+                index.entries.insert(relative, index_entry);
+                continue;
+            }
+
+            let meta = match entry.metadata() {
+                Ok(meta) => meta,
+                Err(e) => {
+                    self.recover(Err::<(), _>(Error::from(e)), failures)?;
+                    continue;
+                }
+            };
+            // A mount point is left out with everything under it
+            if device.is_some_and(|device| meta.dev() != device) {
+                if entry.file_type().is_dir() {
+                    walker.skip_current_dir();
+                }
+                index.excluded.insert(relative.clone());
+                index.mount_points.insert(relative);
+                continue;
+            }
+
+            let Some(index_entry) = IndexEntry::from_metadata(&meta) else {
+                continue;
+            };
+
+            let is_dir = index_entry.kind == EntryKind::Dir;
+            match self.recover(filter.is_excluded(&relative, is_dir), failures)? {
+                Some(false) => {}
+                excluded => {
+                    // An excluded directory is skipped with everything in it
+                    if entry.file_type().is_dir() {
+                        walker.skip_current_dir();
+                    }
+                    if excluded.is_some() {
+                        index.excluded.insert(relative);
+                    }
+                    continue;
+                }
+            }
+
+            if index_entry.kind == EntryKind::Special {
+                let indexed = self.index_special(&mut index, &relative, &meta, is_source);
+                if self.recover(indexed, failures)? != Some(true) {
+                    continue;
+                }
+            }
+
+            if !self.selects(&relative, &index_entry, now) {
+                index.excluded.insert(relative.clone());
+                index.filtered.insert(relative, index_entry);
+                continue;
+            }
+
+            if self.hard_links && index_entry.kind == EntryKind::File && meta.nlink() > 1 {
+                index
+                    .inodes
+                    .insert(relative.clone(), (meta.dev(), meta.ino()));
+            }
+            index.entries.insert(relative, index_entry);
+        }
+        Ok(index)
+    }
+}
@@ -0,0 +1,115 @@
+use super::{named::named_values, sync::SyncData};
+use std::{
+    collections::HashSet,
+    fs::read_link,
+    path::{Component, Path, PathBuf},
+};
+
+/// Most links that are followed in a chain before it is taken as a loop, the same as Linux.
+const MAX_LINK_HOPS: usize = 40;
+
+/// Points to the way symbolic links in the source are synced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LinkPolicy {
+    /// Recreate the link itself in the destination
+    Preserve,
+    /// Copy the file or directory the link points to
+    #[default]
+    Follow,
+    /// Leave the links alone on both sides
+    Skip,
+}
+
+named_values!(LinkPolicy, "link policy", {
+    Preserve => "preserve",
+    Follow => "follow",
+    Skip => "skip",
+});
+
+/// Checks whether resolving a link runs into a loop of links.
+///
+/// Only the chain of the link itself is followed, a directory link that points to one
+/// of its parents is found by the walk instead.
+pub fn is_link_loop(path: &Path) -> bool {
+    let mut visited = HashSet::new();
+    let mut current = path.to_path_buf();
+
+    for _ in 0..MAX_LINK_HOPS {
+        if !visited.insert(current.clone()) {
+            return true;
+        }
+        let Ok(target) = read_link(&current) else {
+            return false;
+        };
+        current = match current.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+    }
+    true
+}
+
+/// Implementation for keeping the symbolic links of the source.
+impl SyncData {
+    /// Gives the target a preserved link gets in the destination.
+    ///
+    /// Takes:
+    /// - Relative path of the link
+    /// - Target that is read from the link in the source
+    ///
+    /// Returns:
+    /// - Target for the destination link
+    ///
+    /// An absolute target inside the source would still point to the source from the
+    /// destination, so it is rewritten relative to the link. Other targets are kept.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::{Path, PathBuf};
+    ///
+    /// let sync = SyncData {
+    ///     source: PathBuf::from("/data/source"),
+    ///     destination: PathBuf::from("/backup"),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let target = sync.link_target(Path::new("docs/latest"), Path::new("/data/source/docs/v2"));
+    /// assert_eq!(target, PathBuf::from("../docs/v2"));
+    /// ```
+    pub fn link_target(&self, link: &Path, target: &Path) -> PathBuf {
+        if !target.is_absolute() {
+            return target.to_path_buf();
+        }
+
+        let inside = target
+            .strip_prefix(&self.source)
+            .ok()
+            .map(Path::to_path_buf)
+            .or_else(|| {
+                let source = self.source.canonicalize().ok()?;
+                target.strip_prefix(source).ok().map(Path::to_path_buf)
+            });
+        let Some(inside) = inside else {
+            return target.to_path_buf();
+        };
+
+        let depth = link
+            .parent()
+            .map(|parent| {
+                parent
+                    .components()
+                    .filter(|c| matches!(c, Component::Normal(_)))
+                    .count()
+            })
+            .unwrap_or(0);
+
+        let mut relative: PathBuf = std::iter::repeat_n(Component::ParentDir, depth).collect();
+        relative.push(inside);
+        if relative.as_os_str().is_empty() {
+            relative.push(".");
+        }
+        relative
+    }
+}
@@ -1,6 +1,6 @@
-use super::sync::SyncData;
+use super::{changes::finish_query, sync::SyncData};
+use crate::error::Result;
 use std::path::PathBuf;
-use walkdir::WalkDir;
 
 /// Implementation for listing all the files and directories.
 impl SyncData {
@@ -8,6 +8,8 @@ impl SyncData {
     ///
     /// Returns:
     /// - List of source files in a vector
+    /// - Error if the source is not a directory or can not be read, `Error::Incomplete` when
+    ///   a path of it fails while continuing on error
     ///
     /// Checks whether the source is actually a directory and walks to find the files in it.
     ///
@@ -24,10 +26,11 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
     /// let mut searched_file: Vec<PathBuf> = Vec::new();
-    /// let searched = sync.list_src_files();
+    /// let searched = sync.list_src_files().expect("[ERROR]: failed to list the files");
     ///
     /// for file in searched {
     ///     let filename = file
@@ -40,37 +43,24 @@ impl SyncData {
     ///
     /// assert!(searched_file.len() != 0);
     /// ```
-    pub fn list_src_files(&self) -> Vec<PathBuf> {
-        let mut src_files_list = Vec::new();
-
-        if !self.source.is_dir() {
-            eprintln!(
-                "[ERROR]: given source '{}' is not a directory",
-                self.source.display()
-            );
-            return Vec::new();
-        }
-
-        for entry in WalkDir::new(&self.source) {
-            let entry_path = entry
-                .as_ref()
-                .expect("[ERROR]: failed to get the path")
-                .path()
-                .to_path_buf();
-
-            if entry_path.is_file() {
-                src_files_list.push(entry_path);
-            }
-        }
-        src_files_list
+    pub fn list_src_files(&self) -> Result<Vec<PathBuf>> {
+        let mut failures = Vec::new();
+        let index = self.scan_tree(&self.source, &mut failures)?;
+        finish_query(
+            index.files().map(|file| index.absolute(file)).collect(),
+            failures,
+        )
     }
 
     /// Gets the list of sub-directories by walking through the source.
     ///
     /// Returns:
     /// - List of source directories in a vector
+    /// - Error if the source is not a directory or can not be read, `Error::Incomplete` when
+    ///   a path of it fails while continuing on error
     ///
     /// Checks whether the source is actually a directory and walks to find the sub-directories in it.
+    /// The source itself is the first directory of the list.
     ///
     /// # Example
     ///
@@ -85,10 +75,11 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
     /// let mut searched_dir: Vec<PathBuf> = Vec::new();
-    /// let searched = sync.list_src_dirs();
+    /// let searched = sync.list_src_dirs().expect("[ERROR]: failed to list the directories");
     ///
     /// for dir in &searched {
     /// let data = dir
@@ -103,35 +94,22 @@ impl SyncData {
     ///
     /// assert!(searched_dir.len() != 0);
     /// ```
-    pub fn list_src_dirs(&self) -> Vec<PathBuf> {
-        let mut src_dirs_list = Vec::new();
-
-        if !self.source.is_dir() {
-            eprintln!(
-                "[ERROR]: given source '{}' is not a directory",
-                self.source.display()
-            );
-            return Vec::new();
-        }
-
-        for entry in WalkDir::new(&self.source) {
-            let entry_path = entry
-                .as_ref()
-                .expect("[ERROR]: failed to get the path")
-                .path()
-                .to_path_buf();
-
-            if entry_path.is_dir() {
-                src_dirs_list.push(entry_path);
-            }
-        }
-        src_dirs_list
+    pub fn list_src_dirs(&self) -> Result<Vec<PathBuf>> {
+        let mut failures = Vec::new();
+        let index = self.scan_tree(&self.source, &mut failures)?;
+        let dirs = index.dirs().map(|dir| index.absolute(dir));
+        finish_query(
+            std::iter::once(self.source.clone()).chain(dirs).collect(),
+            failures,
+        )
     }
 
     /// Gets the list of destination files by walking through the destination.
     ///
     /// Returns:
     /// - List of destination files in a vector
+    /// - Error if the destination is not a directory or can not be read, `Error::Incomplete` when
+    ///   a path of it fails while continuing on error
     ///
     /// Checks whether the destination is actually a directory and walks to find the files in it.
     ///
@@ -148,10 +126,11 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
     /// let mut searched_file: Vec<PathBuf> = Vec::new();
-    /// let searched = sync.list_dest_files();
+    /// let searched = sync.list_dest_files().expect("[ERROR]: failed to list the files");
     ///
     /// for file in searched {
     ///     let filename = file
@@ -164,37 +143,24 @@ impl SyncData {
     ///
     /// assert!(searched_file.len() != 0);
     /// ```
-    pub fn list_dest_files(&self) -> Vec<PathBuf> {
-        let mut dest_files_list = Vec::new();
-
-        if !self.destination.is_dir() {
-            eprintln!(
-                "[ERROR]: given destination '{}' is not a directory",
-                self.destination.display()
-            );
-            return Vec::new();
-        }
-
-        for entry in WalkDir::new(&self.destination) {
-            let entry_path = entry
-                .as_ref()
-                .expect("[ERROR]: failed to get the path")
-                .path()
-                .to_path_buf();
-
-            if entry_path.is_file() {
-                dest_files_list.push(entry_path);
-            }
-        }
-        dest_files_list
+    pub fn list_dest_files(&self) -> Result<Vec<PathBuf>> {
+        let mut failures = Vec::new();
+        let index = self.scan_tree(&self.destination, &mut failures)?;
+        finish_query(
+            index.files().map(|file| index.absolute(file)).collect(),
+            failures,
+        )
     }
 
     /// Gets the list of sub-directories by walking through the destination.
     ///
     /// Returns:
     /// - List of destination directories in a vector
+    /// - Error if the destination is not a directory or can not be read, `Error::Incomplete` when
+    ///   a path of it fails while continuing on error
     ///
     /// Checks whether the destination is actually a directory and walks to find the sub-directories in it.
+    /// The destination itself is the first directory of the list.
     ///
     /// # Example
     ///
@@ -209,10 +175,11 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
     /// let mut searched_dir: Vec<PathBuf> = Vec::new();
-    /// let searched = sync.list_dest_dirs();
+    /// let searched = sync.list_dest_dirs().expect("[ERROR]: failed to list the directories");
     ///
     /// for dir in &searched {
     /// let data = dir
@@ -227,28 +194,15 @@ impl SyncData {
     ///
     /// assert!(searched_dir.len() != 0);
     /// ```
-    pub fn list_dest_dirs(&self) -> Vec<PathBuf> {
-        let mut dest_dirs_list = Vec::new();
-
-        if !self.destination.is_dir() {
-            eprintln!(
-                "[ERROR]: given source '{}' is not a directory",
-                self.destination.display()
-            );
-            return Vec::new();
-        }
-
-        for entry in WalkDir::new(&self.destination) {
-            let entry_path = entry
-                .as_ref()
-                .expect("[ERROR]: failed to get the path")
-                .path()
-                .to_path_buf();
-
-            if entry_path.is_dir() {
-                dest_dirs_list.push(entry_path);
-            }
-        }
-        dest_dirs_list
+    pub fn list_dest_dirs(&self) -> Result<Vec<PathBuf>> {
+        let mut failures = Vec::new();
+        let index = self.scan_tree(&self.destination, &mut failures)?;
+        let dirs = index.dirs().map(|dir| index.absolute(dir));
+        finish_query(
+            std::iter::once(self.destination.clone())
+                .chain(dirs)
+                .collect(),
+            failures,
+        )
     }
 }
@@ -1,5 +1,12 @@
-use super::sync::SyncData;
-use std::{collections::BTreeSet, path::PathBuf};
+use super::{
+    escape::escaped,
+    index::SyncIndex,
+    plan::{OperationKind, SyncOperation, SyncPlan},
+    report::SyncReport,
+    sync::SyncData,
+};
+use crate::error::Result;
+use std::path::PathBuf;
 
 /// Lists the source sub-directories by skipping the parent directory name.
 ///
@@ -24,12 +31,14 @@ use std::{collections::BTreeSet, path::PathBuf};
 ///    delete: false,
 ///    verbose: false,
 ///    dry_run: false,
+///    ..Default::default()
 /// };
 
-/// let list_dirs = sync.list_src_dirs();
+/// let list_dirs = sync.list_src_dirs().expect("[ERROR]: failed to list the directories");
 /// let filtered = filter_src_dir(&list_dirs, &sync.source);
 /// println!("{:?}", filtered);
 /// ```
+#[allow(clippy::empty_line_after_doc_comments)]
 pub fn filter_src_dir(src_dirs: &Vec<PathBuf>, source: &PathBuf) -> Vec<PathBuf> {
     let mut list_src_dirs = Vec::new();
 
@@ -69,12 +78,14 @@ pub fn filter_src_dir(src_dirs: &Vec<PathBuf>, source: &PathBuf) -> Vec<PathBuf>
 ///    delete: false,
 ///    verbose: false,
 ///    dry_run: false,
+///    ..Default::default()
 /// };
 
-/// let list_dirs = sync.list_dest_dirs();
+/// let list_dirs = sync.list_dest_dirs().expect("[ERROR]: failed to list the directories");
 /// let filtered = filter_dest_dir(&list_dirs, &sync.destination);
 /// println!("{:?}", filtered);
 /// ```
+#[allow(clippy::empty_line_after_doc_comments)]
 pub fn filter_dest_dir(dest_dirs: &Vec<PathBuf>, destination: &PathBuf) -> Vec<PathBuf> {
     let mut list_dest_dirs = Vec::new();
 
@@ -113,12 +124,14 @@ pub fn filter_dest_dir(dest_dirs: &Vec<PathBuf>, destination: &PathBuf) -> Vec<P
 ///    delete: false,
 ///    verbose: false,
 ///    dry_run: false,
+///    ..Default::default()
 /// };
 
-/// let list_files = sync.list_src_files();
+/// let list_files = sync.list_src_files().expect("[ERROR]: failed to list the files");
 /// let filtered = filter_src_file(&list_files);
 /// println!("{:?}", filtered);
 /// ```
+#[allow(clippy::empty_line_after_doc_comments)]
 pub fn filter_src_file(src_files: &Vec<PathBuf>) -> Vec<PathBuf> {
     let mut list_src_files = Vec::new();
 
@@ -154,12 +167,14 @@ pub fn filter_src_file(src_files: &Vec<PathBuf>) -> Vec<PathBuf> {
 ///    delete: false,
 ///    verbose: false,
 ///    dry_run: false,
+///    ..Default::default()
 /// };
 
-/// let list_files = sync.list_dest_files();
+/// let list_files = sync.list_dest_files().expect("[ERROR]: failed to list the files");
 /// let filtered = filter_dest_file(&list_files);
 /// println!("{:?}", filtered);
 /// ```
+#[allow(clippy::empty_line_after_doc_comments)]
 pub fn filter_dest_file(dest_files: &Vec<PathBuf>) -> Vec<PathBuf> {
     let mut list_dest_files = Vec::new();
 
@@ -173,13 +188,14 @@ pub fn filter_dest_file(dest_files: &Vec<PathBuf>) -> Vec<PathBuf> {
     list_dest_files
 }
 
-fn print_format(files: &Vec<PathBuf>) {
+#[allow(clippy::len_zero)]
+fn print_format(files: &[PathBuf]) {
     if files.len() == 0 {
         println!("Empty");
     } else if files.len() == 1 {
-        println!("{}", files[0].display());
+        println!("{}", escaped(&files[0]));
     } else {
-        println!("{:?}", files);
+        println!("{}", format_path(files.iter().collect()));
     }
 }
 
@@ -193,18 +209,18 @@ fn format_path(path: Vec<&PathBuf>) -> String {
         format!(
             "[{}]",
             path.iter()
-                .map(|f| f.display().to_string())
+                .map(|f| escaped(f).to_string())
                 .collect::<Vec<_>>()
                 .join(", ")
         )
     }
 }
 
-fn list_data(data: [Vec<PathBuf>; 4], from: [PathBuf; 2]) -> [Vec<PathBuf>; 4] {
-    let list_src_dirs = filter_src_dir(&data[0], &from[0]);
-    let list_dest_dirs = filter_dest_dir(&data[1], &from[1]);
-    let list_src_files = filter_src_file(&data[2]);
-    let list_dest_files = filter_dest_file(&data[3]);
+fn list_data(index: &SyncIndex) {
+    let list_src_dirs: Vec<PathBuf> = index.source.dirs().cloned().collect();
+    let list_dest_dirs: Vec<PathBuf> = index.destination.dirs().cloned().collect();
+    let list_src_files = filter_src_file(&index.source.files().cloned().collect());
+    let list_dest_files = filter_dest_file(&index.destination.files().cloned().collect());
 
     println!("\n[---------LOGS OF ACTION---------]");
 
@@ -219,192 +235,253 @@ fn list_data(data: [Vec<PathBuf>; 4], from: [PathBuf; 2]) -> [Vec<PathBuf>; 4] {
 
     print!("[DESTINATION FILES]: ");
     print_format(&list_dest_files);
+}
+
+fn operation_paths(plan: &SyncPlan, kind: OperationKind) -> Vec<&PathBuf> {
+    plan.iter()
+        .filter(|op| op.kind == kind)
+        .map(|op| &op.path)
+        .collect()
+}
+
+fn sub_plan(plan: &SyncPlan, kinds: &[OperationKind]) -> SyncPlan {
+    SyncPlan {
+        operations: plan
+            .iter()
+            .filter(|op| kinds.contains(&op.kind))
+            .cloned()
+            .collect(),
+        failures: Vec::new(),
+        names: plan.names.clone(),
+    }
+}
 
-    let result: [Vec<PathBuf>; 4] = [
-        list_src_dirs,
-        list_dest_dirs,
-        list_src_files,
-        list_dest_files,
-    ];
-    result
+/// Gives the update operations of the plan for the given files only.
+fn update_plan(plan: &SyncPlan, filenames: &[PathBuf]) -> SyncPlan {
+    let mut updates = sub_plan(plan, &[OperationKind::Update]);
+    updates.retain(|op| filenames.contains(&op.path));
+    updates
 }
 
 /// Implementation of getting the logs of all the actions
 impl SyncData {
-    /// Gives the logs of the files and directories that will be copied from the source
-    ///
-    /// Lists all the source and destination files and directories, gives them the order,
-    /// and pushes their union files and directories in a vector.
-    ///
-    /// Prints the result at the end to show.
-    pub fn src_creation_log(&self) {
-        let src_dirs = self.list_src_dirs();
-        let dest_dirs = self.list_dest_dirs();
-        let src_files = self.list_src_files();
-        let dest_files = self.list_dest_files();
-
-        let data: [Vec<PathBuf>; 4] = [src_dirs, dest_dirs, src_files, dest_files];
-        let from: [PathBuf; 2] = [self.source.clone(), self.destination.clone()];
-        let list_result = list_data(data, from);
-
-        let src_dir_set: BTreeSet<_> = list_result[0].iter().collect();
-        let dest_dir_set: BTreeSet<_> = list_result[1].iter().collect();
-        let src_file_set: BTreeSet<_> = list_result[2].iter().collect();
-        let dest_file_set: BTreeSet<_> = list_result[3].iter().collect();
-        let mut directory: Vec<&PathBuf> = Vec::new();
-        let mut file: Vec<&PathBuf> = Vec::new();
-        let mut done = false;
-
-        for name in src_dir_set.union(&dest_dir_set) {
-            match (src_dir_set.get(name), dest_dir_set.get(name)) {
-                (Some(src), None) => {
-                    directory.push(src);
-                    done = true;
-                }
-                _ => {}
+    /// Prints every mount point that is left out by staying on one file system.
+    pub fn mount_point_log(&self, index: &SyncIndex) {
+        for tree in [&index.source, &index.destination] {
+            for mount_point in &tree.mount_points {
+                println!(
+                    "[PRUNED]: {} is on another file system",
+                    escaped(&tree.absolute(mount_point))
+                );
             }
         }
+    }
 
-        for name in src_file_set.union(&dest_file_set) {
-            match (src_file_set.get(name), dest_file_set.get(name)) {
-                (Some(src), None) => {
-                    file.push(src);
-                    done = true;
-                }
-                _ => {}
-            }
+    /// Gives the logs of the files and directories that will be copied from the source
+    ///
+    /// Takes:
+    /// - Index of the source and destination
+    /// - Plan that is built from the same index
+    ///
+    /// Prints the indexed files and directories, and the directories and files that are
+    /// only in the source. Applies their creation and move operations of the plan,
+    /// and prints every destination entry that is replaced by one of another type as
+    /// `[REPLACED]`, every moved file as `[MOVED]`, every recreated link as `[LINKED]` and
+    /// every recreated FIFO, socket or device node as `[MKNOD]`.
+    pub fn src_creation_log(&self, index: &SyncIndex, plan: &SyncPlan) -> Result<SyncReport> {
+        list_data(index);
+
+        let replaced = operation_paths(plan, OperationKind::Replace);
+        let directory = operation_paths(plan, OperationKind::Mkdir);
+        let file = operation_paths(plan, OperationKind::Create);
+        let linked = operation_paths(plan, OperationKind::Link);
+        let specials = operation_paths(plan, OperationKind::Mknod);
+        let moved: Vec<&SyncOperation> = plan
+            .iter()
+            .filter(|op| op.kind == OperationKind::Move)
+            .collect();
+
+        if replaced.is_empty()
+            && directory.is_empty()
+            && file.is_empty()
+            && linked.is_empty()
+            && specials.is_empty()
+            && moved.is_empty()
+        {
+            return Ok(SyncReport::default());
         }
 
-        if done {
+        if !directory.is_empty() || !file.is_empty() {
             println!(
                 "[DIFFERENCE]: {} directories & {} files",
                 format_path(directory.clone()),
                 format_path(file.clone())
             );
-            println!("[STATUS]: Not matched");
-            self.copy_src_to_dest();
+        }
+        println!("[STATUS]: Not matched");
+        let report = self.apply_plan(&sub_plan(
+            plan,
+            &[
+                OperationKind::Replace,
+                OperationKind::Mkdir,
+                OperationKind::Move,
+                OperationKind::Create,
+                OperationKind::Link,
+                OperationKind::Mknod,
+            ],
+        ))?;
+
+        for path in replaced {
+            println!("[REPLACED]: {}", escaped(path));
+        }
+        for link in linked {
+            println!("[LINKED]: {}", escaped(link));
+        }
+        for special in specials {
+            println!("[MKNOD]: {}", escaped(special));
+        }
+        for op in moved {
+            println!(
+                "[MOVED]: {} -> {}",
+                escaped(op.from.as_deref().unwrap_or(&op.path)),
+                escaped(&op.path)
+            );
+        }
+        if !directory.is_empty() || !file.is_empty() {
             println!(
                 "[COPIED]: {} & {} -> {}",
                 format_path(directory),
                 format_path(file),
-                &self.destination.display()
+                escaped(&self.destination)
             );
         }
+        Ok(report)
     }
 
     /// Gives the logs of the source files that are modified.
     ///
-    /// Lists all the source and destination files and directories,
-    /// and prints the modified files.
+    /// Takes:
+    /// - Index of the source and destination
+    /// - Plan that is built from the same index
+    /// - Files that are modified in the source
     ///
-    /// After giving the status, updates the destination file according to it.
-    pub fn src_modification_log(&self, filenames: Vec<PathBuf>) {
-        let src_dirs = self.list_src_dirs();
-        let dest_dirs = self.list_dest_dirs();
-        let src_files = self.list_src_files();
-        let dest_files = self.list_dest_files();
-
-        let data: [Vec<PathBuf>; 4] = [src_dirs, dest_dirs, src_files, dest_files];
-        let from: [PathBuf; 2] = [self.source.clone(), self.destination.clone()];
-        let _ = list_data(data, from);
+    /// Prints the indexed files and directories, and the modified files.
+    ///
+    /// After giving the status, applies the update operations of the plan for these files.
+    pub fn src_modification_log(
+        &self,
+        index: &SyncIndex,
+        plan: &SyncPlan,
+        filenames: Vec<PathBuf>,
+    ) -> Result<SyncReport> {
+        list_data(index);
 
         print!("[MODIFIED FILES]: ");
         print_format(&filenames);
 
         println!("[STATUS]: Not matched");
 
-        self.update_dest_file(filenames.clone());
+        let report = self.apply_plan(&update_plan(plan, &filenames))?;
 
         print!("[UPDATED FILES]: ");
         print_format(&filenames);
+        Ok(report)
     }
 
     /// Gives the logs of the files and directories that will be removed from the destination
     ///
-    /// Lists all the source and destination files and directories, gives them the order,
-    /// and pushes their union files and directories in a vector.
+    /// Takes:
+    /// - Index of the source and destination
+    /// - Plan that is built from the same index
     ///
-    /// Prints the result at the end to show.
-    pub fn dest_creation_log(&self) {
-        let src_dirs = self.list_src_dirs();
-        let dest_dirs = self.list_dest_dirs();
-        let src_files = self.list_src_files();
-        let dest_files = self.list_dest_files();
-
-        let data: [Vec<PathBuf>; 4] = [src_dirs, dest_dirs, src_files, dest_files];
-        let from: [PathBuf; 2] = [self.source.clone(), self.destination.clone()];
-        let list_result = list_data(data, from);
-
-        let src_dir_set: BTreeSet<_> = list_result[0].iter().collect();
-        let dest_dir_set: BTreeSet<_> = list_result[1].iter().collect();
-        let src_file_set: BTreeSet<_> = list_result[2].iter().collect();
-        let dest_file_set: BTreeSet<_> = list_result[3].iter().collect();
-        let mut directory: Vec<&PathBuf> = Vec::new();
-        let mut file: Vec<&PathBuf> = Vec::new();
-        let mut done = false;
-
-        for name in src_dir_set.union(&dest_dir_set) {
-            match (src_dir_set.get(name), dest_dir_set.get(name)) {
-                (None, Some(dest)) => {
-                    directory.push(dest);
-                    done = true;
-                }
-                _ => {}
-            }
-        }
+    /// Prints the indexed files and directories, and the directories and files that are
+    /// only in the destination. Applies their removal operations of the plan.
+    pub fn dest_creation_log(&self, index: &SyncIndex, plan: &SyncPlan) -> Result<SyncReport> {
+        list_data(index);
 
-        for name in src_file_set.union(&dest_file_set) {
-            match (src_file_set.get(name), dest_file_set.get(name)) {
-                (None, Some(dest)) => {
-                    file.push(dest);
-                    done = true;
-                }
-                _ => {}
-            }
-        }
+        let directory = operation_paths(plan, OperationKind::Rmdir);
+        let file = operation_paths(plan, OperationKind::Delete);
 
-        if done {
+        if !directory.is_empty() || !file.is_empty() {
             println!(
                 "[DIFFERENCE]: {} directories & {} files",
                 format_path(directory.clone()),
                 format_path(file.clone())
             );
             println!("[STATUS]: Not matched");
-            self.remove_dest_file();
+            let report = self.apply_plan(&sub_plan(
+                plan,
+                &[OperationKind::Delete, OperationKind::Rmdir],
+            ))?;
             println!(
                 "[REMOVED]: {} directories & {} files from {}",
                 format_path(directory),
                 format_path(file),
-                &self.destination.display()
+                escaped(&self.destination)
+            );
+            return Ok(report);
+        }
+        Ok(SyncReport::default())
+    }
+
+    /// Gives the logs of the files that are linked to their hard link group.
+    ///
+    /// Takes:
+    /// - Plan that is built from the index
+    ///
+    /// Applies the hard link operations of the plan and prints every link as `[HARDLINKED]`.
+    pub fn hard_link_log(&self, plan: &SyncPlan) -> Result<SyncReport> {
+        let report = self.apply_plan(&sub_plan(plan, &[OperationKind::HardLink]))?;
+
+        for op in plan.iter().filter(|op| op.kind == OperationKind::HardLink) {
+            println!(
+                "[HARDLINKED]: {} -> {}",
+                escaped(op.from.as_deref().unwrap_or(&op.path)),
+                escaped(&op.path)
             );
         }
+        Ok(report)
+    }
+
+    /// Gives the logs of the entries whose metadata is copied in archive mode.
+    ///
+    /// Takes:
+    /// - Plan that is built from the index
+    ///
+    /// Applies the metadata operations of the plan and prints every entry as `[ATTRS]`.
+    pub fn attribute_log(&self, plan: &SyncPlan) -> Result<SyncReport> {
+        let report = self.apply_plan(&sub_plan(plan, &[OperationKind::Attrs]))?;
+
+        for path in operation_paths(plan, OperationKind::Attrs) {
+            println!("[ATTRS]: {}", escaped(path));
+        }
+        Ok(report)
     }
 
     /// Gives the logs of the destination files that are modified.
     ///
-    /// Lists all the source and destination files and directories,
-    /// and prints the modified files.
+    /// Prints the indexed files and directories, and the modified files.
     ///
-    /// After giving the status, removed the destination file content.
+    /// After giving the status, removed the destination file content with the update
+    /// operations of the plan for these files.
     /// The destination file content is not allowed to be modified.
-    pub fn dest_modification_log(&self, filenames: Vec<PathBuf>) {
-        let src_dirs = self.list_src_dirs();
-        let dest_dirs = self.list_dest_dirs();
-        let src_files = self.list_src_files();
-        let dest_files = self.list_dest_files();
-
-        let data: [Vec<PathBuf>; 4] = [src_dirs, dest_dirs, src_files, dest_files];
-        let from: [PathBuf; 2] = [self.source.clone(), self.destination.clone()];
-        let _ = list_data(data, from);
+    pub fn dest_modification_log(
+        &self,
+        index: &SyncIndex,
+        plan: &SyncPlan,
+        filenames: Vec<PathBuf>,
+    ) -> Result<SyncReport> {
+        list_data(index);
 
         print!("[MODIFIED FILES]: ");
         print_format(&filenames);
 
         println!("[STATUS]: Not matched");
 
-        self.update_dest_file(filenames.clone());
+        let report = self.apply_plan(&update_plan(plan, &filenames))?;
 
         print!("[REMOVED FILE CONTENT]: ");
         print_format(&filenames);
+        Ok(report)
     }
 }
@@ -0,0 +1,120 @@
+use super::{
+    named::named_values,
+    plan::{OperationKind, SyncPlan},
+    report::SyncReport,
+    sync::SyncData,
+};
+use crate::error::Result;
+
+/// Points to the moment the extra destination entries are removed while mirroring.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteTiming {
+    /// Remove them before any file is copied, which frees the space first
+    Before,
+    /// Scan the destination again once every file is copied and remove what the source lacks
+    #[default]
+    After,
+    /// Remove the entries found by the first scan once every file is copied
+    Delay,
+}
+
+named_values!(DeleteTiming, "delete timing", {
+    Before => "before",
+    After => "after",
+    Delay => "delay",
+});
+
+/// Checks whether an operation removes an entry of the destination.
+fn is_removal(kind: OperationKind) -> bool {
+    matches!(kind, OperationKind::Delete | OperationKind::Rmdir)
+}
+
+/// Implementation for mirroring the source into the destination.
+impl SyncData {
+    /// Applies a sync plan and removes the extra destination entries at the chosen time.
+    ///
+    /// Takes:
+    /// - The sync plan of the source and destination
+    /// - Whether the source and destination are scanned without a failure
+    ///
+    /// Returns:
+    /// - Report of the applied operations and the paths that failed
+    /// - Error of the first failed path, unless the sync continues on error
+    ///
+    /// The removals run before the transfers, or after them with the entries of the plan
+    /// (`delay`) or of a new scan of the destination (`after`). The removals of the new scan are
+    /// checked against the limits and confirmed again. When a scan fails, or a transfer
+    /// before them, the removals are left out, since a path that could not be read looks extra. The
+    /// metadata of the directories is set last, once their entries are removed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::mirror::DeleteTiming;
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::PathBuf;
+    ///
+    /// let sync = SyncData {
+    ///     source: PathBuf::from("source_directory"),
+    ///     destination: PathBuf::from("destination_directory"),
+    ///     mirror: true,
+    ///     delete_timing: DeleteTiming::Delay,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    /// let report = sync.apply_mirror(&plan, plan.failures.is_empty()).expect("[ERROR]: failed to mirror the source");
+    /// report.print_failures();
+    /// ```
+    pub fn apply_mirror(&self, plan: &SyncPlan, scanned: bool) -> Result<SyncReport> {
+        let part = |keep: fn(OperationKind) -> bool| SyncPlan {
+            operations: plan.iter().filter(|op| keep(op.kind)).cloned().collect(),
+            failures: Vec::new(),
+            names: plan.names.clone(),
+        };
+        let removals = part(is_removal);
+        let transfers = part(|kind| !is_removal(kind) && kind != OperationKind::Attrs);
+        let attributes = part(|kind| kind == OperationKind::Attrs);
+
+        let kept = || {
+            println!(
+                "[MESSAGE]: extra file(s) are kept in the destination, since some paths failed"
+            );
+        };
+        let scanned = scanned && plan.failures.is_empty();
+
+        let mut report = SyncReport::default();
+        if self.delete_timing == DeleteTiming::Before {
+            match scanned {
+                true => report.merge(self.apply_plan(&removals)?),
+                false => kept(),
+            }
+        }
+        report.merge(self.apply_plan(&transfers)?);
+
+        if self.delete_timing != DeleteTiming::Before {
+            let removals = match self.delete_timing {
+                DeleteTiming::After => {
+                    let mut index = self.scan()?;
+                    let mut rescanned = self.plan_from_index(&index)?;
+                    rescanned.retain(|op| is_removal(op.kind));
+                    rescanned.failures.append(&mut index.failures);
+                    // The removals of the new scan are the ones that are limited and confirmed
+                    self.guard_removals(rescanned.len(), index.destination.entries.len())?;
+                    rescanned
+                }
+                _ => removals,
+            };
+
+            if scanned && report.is_success() && removals.failures.is_empty() {
+                report.merge(self.apply_plan(&removals)?);
+            } else {
+                kept();
+                report.failures.extend(removals.failures);
+            }
+        }
+
+        report.merge(self.apply_plan(&attributes)?);
+        Ok(report)
+    }
+}
@@ -1,7 +1,28 @@
+pub mod archive;
 pub mod changes;
+pub mod checksum;
+pub mod collision;
+pub mod delta;
+pub mod escape;
+pub mod exclude;
 pub mod filter;
+pub mod gitignore;
+pub mod guard;
+pub mod index;
+pub mod links;
 pub mod listing;
 pub mod log;
+pub mod mirror;
+pub mod moves;
+pub mod named;
+pub mod plan;
+pub mod pool;
+pub mod report;
+pub mod select;
+pub mod sparse;
+pub mod special;
+#[allow(clippy::module_inception)]
 pub mod sync;
+pub mod target;
 pub mod timestamp;
 pub mod trigger;
@@ -0,0 +1,93 @@
+use super::{
+    index::SyncIndex,
+    plan::{OperationKind, SyncOperation},
+    sync::SyncData,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+};
+
+/// Implementation for finding the files that are renamed or moved in the source.
+impl SyncData {
+    /// Matches the files that vanished from the source with the new source files.
+    ///
+    /// Takes:
+    /// - Index of the source and destination
+    /// - Relative paths of the files that are only in the source
+    /// - Relative paths of the files that are only in the destination
+    ///
+    /// Returns:
+    /// - Move operation for every pair with the same size and content hash, in the order of the new files
+    ///
+    /// Only the files that share their size with a file of the other side are hashed,
+    /// on up to `jobs` threads. Empty files are never matched, since creating them is
+    /// as cheap as renaming. A file that can not be hashed is left to be copied.
+    pub fn detect_moves(
+        &self,
+        index: &SyncIndex,
+        created: &[&PathBuf],
+        deleted: &[&PathBuf],
+    ) -> Vec<SyncOperation> {
+        let mut by_size: BTreeMap<u64, Vec<&PathBuf>> = BTreeMap::new();
+        for file in deleted {
+            let size = index.destination.size_of(file);
+            if size > 0 {
+                by_size.entry(size).or_default().push(file);
+            }
+        }
+
+        let candidates: Vec<&PathBuf> = created
+            .iter()
+            .copied()
+            .filter(|file| by_size.contains_key(&index.source.size_of(file)))
+            .collect();
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+
+        let vanished: Vec<&PathBuf> = candidates
+            .iter()
+            .flat_map(|file| by_size[&index.source.size_of(file)].iter().copied())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let src_hashes = self.run_parallel(&candidates, |file| {
+            Ok(self.file_checksum(&index.source.absolute(file)).ok())
+        });
+        let dest_hashes = self.run_parallel(&vanished, |file| {
+            Ok(self.file_checksum(&index.destination.absolute(file)).ok())
+        });
+
+        let mut unclaimed: HashMap<(u64, [u8; 32]), Vec<&PathBuf>> = HashMap::new();
+        for (file, hash) in vanished.iter().zip(dest_hashes) {
+            if let Ok(Some(hash)) = hash {
+                let size = index.destination.size_of(file);
+                unclaimed.entry((size, hash)).or_default().push(file);
+            }
+        }
+        // The first vanished path in order is claimed first
+        for paths in unclaimed.values_mut() {
+            paths.reverse();
+        }
+
+        let mut moves = Vec::new();
+        for (file, hash) in candidates.iter().zip(src_hashes) {
+            let size = index.source.size_of(file);
+            if let Ok(Some(hash)) = hash
+                && let Some(from) = unclaimed
+                    .get_mut(&(size, hash))
+                    .and_then(|paths| paths.pop())
+            {
+                moves.push(SyncOperation {
+                    kind: OperationKind::Move,
+                    path: (*file).clone(),
+                    size,
+                    from: Some(from.clone()),
+                });
+            }
+        }
+        moves
+    }
+}
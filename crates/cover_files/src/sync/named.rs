@@ -0,0 +1,49 @@
+/// A value that is picked by its name on the command line.
+///
+/// The implementations are made with `named_values!`, which lists every variant with its
+/// name once.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cover_files::sync::{links::LinkPolicy, named::Named};
+///
+/// assert_eq!(LinkPolicy::from_name("Preserve"), Some(LinkPolicy::Preserve));
+/// assert_eq!(LinkPolicy::Skip.name(), "skip");
+/// assert_eq!(LinkPolicy::KIND, "link policy");
+/// ```
+pub trait Named: Sized + Copy + 'static {
+    /// What the value sets, for the messages about an unknown name
+    const KIND: &'static str;
+    /// Every value, in the order they are listed in the help
+    const ALL: &'static [Self];
+
+    /// Gives the name of the value that is used on the command line.
+    fn name(self) -> &'static str;
+
+    /// Finds the value with the given name, ignoring the case.
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|value| value.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Implements `Named` for an enum from its kind and the name of every variant.
+macro_rules! named_values {
+    ($type:ident, $kind:literal, { $($variant:ident => $name:literal),+ $(,)? }) => {
+        impl $crate::sync::named::Named for $type {
+            const KIND: &'static str = $kind;
+            const ALL: &'static [Self] = &[$($type::$variant),+];
+
+            fn name(self) -> &'static str {
+                match self {
+                    $($type::$variant => $name),+
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use named_values;
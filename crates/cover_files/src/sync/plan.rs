@@ -0,0 +1,415 @@
+use super::{escape::escaped, index::SyncIndex, special::changed_specials, sync::SyncData};
+use crate::error::{Error, Result};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::PathBuf,
+};
+
+/// Points to the kind of operation that is planned for a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OperationKind {
+    /// Remove a destination entry, with everything in it, whose type differs from the source one
+    Replace,
+    /// Create a directory in the destination
+    Mkdir,
+    /// Rename a destination file that is only in the destination to the path of a new source file
+    Move,
+    /// Copy a new source file to the destination
+    Create,
+    /// Overwrite a destination file with the source file
+    Update,
+    /// Create or replace a symbolic link in the destination with the target of the source link
+    Link,
+    /// Create or replace a FIFO, socket or device node in the destination like the source one
+    Mknod,
+    /// Link a file to the first file of its hard link group in the destination
+    HardLink,
+    /// Remove a destination file or link that is not in the source
+    Delete,
+    /// Remove a destination directory that is not in the source
+    Rmdir,
+    /// Copy only the timestamps, permissions, owner and extended attributes in archive mode
+    Attrs,
+}
+
+impl fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OperationKind::Replace => "REPLACE",
+            OperationKind::Mkdir => "MKDIR",
+            OperationKind::Move => "MOVE",
+            OperationKind::Create => "CREATE",
+            OperationKind::Update => "UPDATE",
+            OperationKind::Link => "LINK",
+            OperationKind::Mknod => "MKNOD",
+            OperationKind::HardLink => "HARDLINK",
+            OperationKind::Delete => "DELETE",
+            OperationKind::Rmdir => "RMDIR",
+            OperationKind::Attrs => "ATTRS",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A single operation of the sync plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncOperation {
+    pub kind: OperationKind,
+    /// Path relative to the source and destination directories
+    pub path: PathBuf,
+    /// Size in bytes of the file that is copied, moved or removed
+    pub size: u64,
+    /// Relative path in the destination that a moved file is renamed from, or that a hard link is made to
+    pub from: Option<PathBuf>,
+}
+
+/// Holds every operation that is required to bring the destination in sync with the source.
+///
+/// The operations are ordered so they can be applied one after the other:
+/// the destination entries whose type differs from the source are removed first,
+/// then directories are created, then files are moved, created and updated,
+/// then the extra files and directories are removed. In archive mode the metadata
+/// of the entries is set at last.
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    pub operations: Vec<SyncOperation>,
+    /// Paths that could not be scanned or compared while continuing on error
+    pub failures: Vec<Error>,
+    /// Relative paths on the disk of the destination entries that are not at the mapped path of their key
+    pub names: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl SyncPlan {
+    /// Checks whether the plan has nothing to do.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Gives the number of the planned operations.
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Iterates over the planned operations in the order they are applied.
+    pub fn iter(&self) -> impl Iterator<Item = &SyncOperation> {
+        self.operations.iter()
+    }
+
+    /// Keeps only the operations for which the predicate returns true.
+    pub fn retain<F: FnMut(&SyncOperation) -> bool>(&mut self, f: F) {
+        self.operations.retain(f);
+    }
+
+    /// Checks whether the plan has any operation of the given kind.
+    pub fn contains(&self, kind: OperationKind) -> bool {
+        self.operations.iter().any(|op| op.kind == kind)
+    }
+
+    /// Gives the total number of bytes that are copied by the plan.
+    pub fn transfer_size(&self) -> u64 {
+        self.operations
+            .iter()
+            .filter(|op| matches!(op.kind, OperationKind::Create | OperationKind::Update))
+            .map(|op| op.size)
+            .sum()
+    }
+}
+
+/// Implementation for planning the sync without touching the destination.
+impl SyncData {
+    /// Builds the plan of every operation that is needed to sync the destination.
+    ///
+    /// Returns:
+    /// - The sync plan with the relative path and size of each operation
+    /// - Error if a path can not be listed or compared
+    ///
+    /// Scans the source and destination once, compares their relative paths
+    /// and finds the modified files. Nothing is changed on the disk, so the plan can be
+    /// inspected or filtered before it is applied with `apply_plan`.
+    ///
+    /// When the sync continues on error, the paths that fail are kept in the plan failures
+    /// and left out of the operations.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::plan::OperationKind;
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::PathBuf;
+    ///
+    /// let sync = SyncData {
+    ///     source: PathBuf::from("source_directory"),
+    ///     destination: PathBuf::from("destination_directory"),
+    ///     changed_only: true,
+    ///     delete: false,
+    ///     dry_run: false,
+    ///     verbose: false,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let mut plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    /// plan.retain(|op| op.kind != OperationKind::Delete);
+    /// sync.apply_plan(&plan).expect("[ERROR]: failed to apply the plan");
+    /// ```
+    pub fn plan(&self) -> Result<SyncPlan> {
+        self.scan_and_plan(true)
+    }
+
+    /// Scans both sides and plans the sync, with or without matching the renamed files.
+    pub(crate) fn scan_and_plan(&self, with_moves: bool) -> Result<SyncPlan> {
+        let mut index = self.scan()?;
+        let mut plan = self.build_plan(&index, with_moves)?;
+
+        index.failures.append(&mut plan.failures);
+        plan.failures = index.failures;
+        Ok(plan)
+    }
+
+    /// Builds the sync plan from an index that is already scanned.
+    ///
+    /// Takes:
+    /// - Index of the source and destination
+    ///
+    /// Returns:
+    /// - The sync plan with the relative path and size of each operation
+    /// - Error if a file that is present on both sides can not be compared
+    ///
+    /// Sizes and timestamps are taken from the index, only the files whose timestamps
+    /// differ are read again to compare their content. The files are compared on up to
+    /// `jobs` threads and the operations keep the same order.
+    pub fn plan_from_index(&self, index: &SyncIndex) -> Result<SyncPlan> {
+        self.build_plan(index, true)
+    }
+
+    /// Builds the sync plan, where a renamed file is copied and removed unless `with_moves` is set.
+    fn build_plan(&self, index: &SyncIndex, with_moves: bool) -> Result<SyncPlan> {
+        let mut failures = Vec::new();
+        let src = &index.source;
+        let dest = &index.destination;
+
+        // Every other path of a hard link group is linked to the first one instead of copied
+        let followers: BTreeMap<&PathBuf, &PathBuf> = src
+            .link_groups()
+            .into_iter()
+            .flat_map(|group| {
+                let leader = group[0];
+                group.into_iter().skip(1).map(move |path| (path, leader))
+            })
+            .collect();
+
+        let common: Vec<&PathBuf> = src
+            .files()
+            .filter(|file| dest.is_file(file) && !followers.contains_key(file))
+            .collect();
+        let compared = self.run_parallel(&common, |file| self.file_changed(file, index));
+
+        let mut modified = Vec::new();
+        for (file, changed) in common.into_iter().zip(compared) {
+            if let Some(true) = self.recover(changed, &mut failures)? {
+                modified.push(file);
+            }
+        }
+
+        let mut operations = Vec::new();
+
+        // A directory in the place of a file, or the other way round, is removed before the
+        // source entry is created. A directory that holds excluded paths is left in the way.
+        let replaced: Vec<&PathBuf> = dest
+            .entries
+            .keys()
+            .filter(|path| src.contains(path) && src.is_dir(path) != dest.is_dir(path))
+            .filter(|path| !dest.holds_excluded(path))
+            .collect();
+        let is_replaced =
+            |path: &PathBuf| replaced.iter().any(|replaced| path.starts_with(replaced));
+        for path in &replaced {
+            operations.push(SyncOperation {
+                kind: OperationKind::Replace,
+                path: (*path).clone(),
+                size: dest.size_of(path),
+                from: None,
+            });
+        }
+
+        let mut new_dirs: Vec<&PathBuf> = src.dirs().filter(|dir| !dest.is_dir(dir)).collect();
+        new_dirs.sort_by_key(|dir| dir.components().count());
+        for dir in new_dirs {
+            operations.push(SyncOperation {
+                kind: OperationKind::Mkdir,
+                path: dir.clone(),
+                size: 0,
+                from: None,
+            });
+        }
+
+        let created: Vec<&PathBuf> = src
+            .files()
+            .filter(|file| !dest.is_file(file) && !followers.contains_key(file))
+            .collect();
+        // A file that becomes a link or special file is replaced by it, not removed after it
+        let deleted: Vec<&PathBuf> = dest
+            .files()
+            .filter(|file| !src.is_file(file) && !src.is_link(file) && !src.is_special(file))
+            .filter(|file| !is_replaced(file))
+            .collect();
+
+        // A renamed file is moved inside the destination instead of being copied again
+        let moves = match with_moves {
+            true => self.detect_moves(index, &created, &deleted),
+            false => Vec::new(),
+        };
+        let moved_from: BTreeSet<&PathBuf> =
+            moves.iter().filter_map(|op| op.from.as_ref()).collect();
+        let moved_to: BTreeSet<&PathBuf> = moves.iter().map(|op| &op.path).collect();
+        operations.extend(moves.iter().cloned());
+
+        for file in created.iter().filter(|file| !moved_to.contains(*file)) {
+            operations.push(SyncOperation {
+                kind: OperationKind::Create,
+                path: (*file).clone(),
+                size: src.size_of(file),
+                from: None,
+            });
+        }
+
+        let copied: BTreeSet<&PathBuf> = created.iter().chain(&modified).copied().collect();
+        for file in modified {
+            operations.push(SyncOperation {
+                kind: OperationKind::Update,
+                path: file.clone(),
+                size: src.size_of(file),
+                from: None,
+            });
+        }
+
+        for link in src.links() {
+            if dest.link_target(link) != src.link_target(link) {
+                operations.push(SyncOperation {
+                    kind: OperationKind::Link,
+                    path: link.clone(),
+                    size: 0,
+                    from: None,
+                });
+            }
+        }
+
+        for special in changed_specials(src, dest) {
+            operations.push(SyncOperation {
+                kind: OperationKind::Mknod,
+                path: special.clone(),
+                size: 0,
+                from: None,
+            });
+        }
+
+        // A copied file is a new file, so the links to it are made again
+        for (file, leader) in followers {
+            if copied.contains(leader) || !dest.same_file(file, leader) {
+                operations.push(SyncOperation {
+                    kind: OperationKind::HardLink,
+                    path: file.clone(),
+                    size: 0,
+                    from: Some(leader.clone()),
+                });
+            }
+        }
+
+        for file in deleted
+            .into_iter()
+            .filter(|file| !moved_from.contains(file))
+        {
+            operations.push(SyncOperation {
+                kind: OperationKind::Delete,
+                path: file.clone(),
+                size: dest.size_of(file),
+                from: None,
+            });
+        }
+
+        for link in dest
+            .links()
+            .chain(dest.specials())
+            .filter(|path| !src.contains(path) && !is_replaced(path))
+        {
+            operations.push(SyncOperation {
+                kind: OperationKind::Delete,
+                path: link.clone(),
+                size: 0,
+                from: None,
+            });
+        }
+
+        // A directory that holds excluded paths is left in the destination
+        let mut extra_dirs: Vec<&PathBuf> = dest
+            .dirs()
+            .filter(|dir| !src.is_dir(dir) && !dest.holds_excluded(dir) && !is_replaced(dir))
+            .collect();
+        extra_dirs.sort_by_key(|dir| Reverse(dir.components().count()));
+        for dir in extra_dirs {
+            operations.push(SyncOperation {
+                kind: OperationKind::Rmdir,
+                path: dir.clone(),
+                size: 0,
+                from: None,
+            });
+        }
+
+        if self.archive {
+            let attributes = self.plan_attributes(index, &operations, &mut failures)?;
+            operations.extend(attributes);
+        }
+
+        Ok(SyncPlan {
+            operations,
+            failures,
+            names: self.dest_names(dest),
+        })
+    }
+
+    /// Prints every operation of the plan along with its relative path and size.
+    pub fn print_plan(&self, plan: &SyncPlan) {
+        if plan.is_empty() {
+            println!("[DRY RUN]: no changes detected");
+            return;
+        }
+
+        for op in plan.iter() {
+            match op.kind {
+                OperationKind::Replace
+                | OperationKind::Create
+                | OperationKind::Update
+                | OperationKind::Delete => {
+                    println!(
+                        "[DRY RUN]: {} {} ({} bytes)",
+                        op.kind,
+                        escaped(&op.path),
+                        op.size
+                    )
+                }
+                OperationKind::Move | OperationKind::HardLink => {
+                    println!(
+                        "[DRY RUN]: {} {} -> {} ({} bytes)",
+                        op.kind,
+                        escaped(op.from.as_deref().unwrap_or(&op.path)),
+                        escaped(&op.path),
+                        op.size
+                    )
+                }
+                OperationKind::Mkdir
+                | OperationKind::Link
+                | OperationKind::Mknod
+                | OperationKind::Rmdir
+                | OperationKind::Attrs => {
+                    println!("[DRY RUN]: {} {}", op.kind, escaped(&op.path))
+                }
+            }
+        }
+        println!(
+            "[DRY RUN]: {} operation(s), {} bytes to copy",
+            plan.len(),
+            plan.transfer_size()
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use super::sync::SyncData;
+use crate::error::Result;
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+/// Implementation of the bounded worker pool used for comparing, hashing and copying.
+impl SyncData {
+    /// Gives the number of threads that are used for the work.
+    ///
+    /// Zero and one both run the work on the current thread.
+    pub fn worker_count(&self) -> usize {
+        self.jobs.max(1)
+    }
+
+    /// Runs the task for every item on at most `jobs` threads.
+    ///
+    /// Takes:
+    /// - Items to work on
+    /// - Task that is run for each item
+    ///
+    /// Returns:
+    /// - Result of every item that is processed, in the order of the items
+    ///
+    /// The items are handed out one by one in their order. When an item fails and the sync
+    /// does not continue on error, no new item is started, so the results are always the
+    /// leading part of the items and the first failure is the same as in a single thread.
+    pub(crate) fn run_parallel<T, R, F>(&self, items: &[T], task: F) -> Vec<Result<R>>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&T) -> Result<R> + Sync,
+    {
+        self.run_on(self.worker_count(), items, task)
+    }
+
+    /// Runs the task for every item on the given number of threads.
+    pub(crate) fn run_on<T, R, F>(&self, workers: usize, items: &[T], task: F) -> Vec<Result<R>>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&T) -> Result<R> + Sync,
+    {
+        let workers = workers.min(items.len());
+        if workers <= 1 {
+            let mut results = Vec::with_capacity(items.len());
+            for item in items {
+                let result = task(item);
+                let failed = result.is_err();
+                results.push(result);
+                if failed && !self.continue_on_error {
+                    break;
+                }
+            }
+            return results;
+        }
+
+        let next = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let done = Mutex::new(Vec::with_capacity(items.len()));
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    loop {
+                        if stop.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        let position = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(item) = items.get(position) else {
+                            break;
+                        };
+
+                        let result = task(item);
+                        if result.is_err() && !self.continue_on_error {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                        done.lock()
+                            .expect("[ERROR]: worker pool lock is poisoned")
+                            .push((position, result));
+                    }
+                });
+            }
+        });
+
+        let mut done = done
+            .into_inner()
+            .expect("[ERROR]: worker pool lock is poisoned");
+        done.sort_by_key(|(position, _)| *position);
+        done.into_iter().map(|(_, result)| result).collect()
+    }
+}
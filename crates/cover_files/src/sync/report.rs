@@ -0,0 +1,55 @@
+use super::escape::escaped;
+use crate::error::Error;
+use std::path::PathBuf;
+
+/// Holds the outcome of applying a sync plan.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Number of operations that are applied successfully
+    pub applied: usize,
+    /// Bytes of the updated files that are left as they are by the delta updates
+    pub bytes_saved: u64,
+    /// Paths that failed while continuing on error
+    pub failures: Vec<Error>,
+    /// Paths that are left out of the sync with the reason, like links that run into a loop
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+impl SyncReport {
+    /// Checks whether every path is synced without a failure.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Adds the operations and failures of another report to this one.
+    pub fn merge(&mut self, other: SyncReport) {
+        self.applied += other.applied;
+        self.bytes_saved += other.bytes_saved;
+        self.failures.extend(other.failures);
+        self.skipped.extend(other.skipped);
+    }
+
+    /// Prints the bytes saved by the delta updates, when there are any.
+    pub fn print_delta(&self) {
+        if self.bytes_saved > 0 {
+            println!(
+                "[DELTA]: {} bytes saved by updating changed blocks only",
+                self.bytes_saved
+            );
+        }
+    }
+
+    /// Prints one line for every path that is left out with its reason.
+    pub fn print_skipped(&self) {
+        for (path, reason) in &self.skipped {
+            eprintln!("[SKIPPED]: {}: {}", escaped(path), reason);
+        }
+    }
+
+    /// Prints one line for every path that failed.
+    pub fn print_failures(&self) {
+        for failure in &self.failures {
+            eprintln!("[FAILED]: {}", failure);
+        }
+    }
+}
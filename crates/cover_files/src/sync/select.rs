@@ -0,0 +1,153 @@
+use super::{
+    index::{EntryKind, IndexEntry},
+    named::named_values,
+    sync::SyncData,
+};
+use std::{
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Named group of file extensions that can be selected at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Code,
+}
+
+named_values!(FileType, "file type", {
+    Image => "image",
+    Video => "video",
+    Audio => "audio",
+    Document => "document",
+    Archive => "archive",
+    Code => "code",
+});
+
+impl FileType {
+    /// Gives the lowercase extensions of the type, without the dot.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            FileType::Image => &[
+                "png", "jpg", "jpeg", "gif", "bmp", "tif", "tiff", "webp", "svg", "heic", "raw",
+            ],
+            FileType::Video => &[
+                "mp4", "mkv", "mov", "avi", "webm", "wmv", "m4v", "mpg", "mpeg",
+            ],
+            FileType::Audio => &["mp3", "wav", "flac", "ogg", "m4a", "aac", "opus", "wma"],
+            FileType::Document => &[
+                "pdf", "doc", "docx", "odt", "rtf", "txt", "md", "xls", "xlsx", "ods", "csv",
+                "ppt", "pptx", "odp", "epub",
+            ],
+            FileType::Archive => &["zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar"],
+            FileType::Code => &[
+                "rs", "c", "h", "cpp", "hpp", "py", "js", "ts", "go", "java", "rb", "sh", "toml",
+                "json", "yaml", "yml",
+            ],
+        }
+    }
+}
+
+/// Gives the time since the unix epoch the age filters are measured from.
+pub fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Implementation for selecting the files by their size, age and type.
+impl SyncData {
+    /// Checks whether any of the size, age or type filters is given.
+    pub fn has_file_selection(&self) -> bool {
+        self.min_size.is_some()
+            || self.max_size.is_some()
+            || self.newer_than.is_some()
+            || self.older_than.is_some()
+            || !self.extensions.is_empty()
+            || !self.file_types.is_empty()
+    }
+
+    /// Checks whether a file passes the size, age and type filters.
+    ///
+    /// Takes:
+    /// - Relative path of the entry
+    /// - Entry with the size and modification time
+    /// - Current time since the unix epoch, the ages are measured from it
+    ///
+    /// Returns:
+    /// - Boolean to show the entry is synced, directories always are
+    ///
+    /// The bounds of the sizes are inclusive. With both extensions and types given, a
+    /// file that matches any of them is selected.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::index::{EntryKind, IndexEntry};
+    /// use cover_files::sync::select::{FileType, now};
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::Path;
+    /// use std::time::Duration;
+    ///
+    /// let sync = SyncData {
+    ///     max_size: Some(100 * 1024 * 1024),
+    ///     newer_than: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+    ///     file_types: vec![FileType::Document],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let entry = IndexEntry {
+    ///     kind: EntryKind::File,
+    ///     size: 2048,
+    ///     modified: now(),
+    /// };
+    /// assert!(sync.selects(Path::new("report.pdf"), &entry, now()));
+    /// assert!(!sync.selects(Path::new("photo.png"), &entry, now()));
+    /// ```
+    pub fn selects(&self, path: &Path, entry: &IndexEntry, now: Duration) -> bool {
+        if entry.kind != EntryKind::File {
+            return true;
+        }
+
+        if self.min_size.is_some_and(|min| entry.size < min)
+            || self.max_size.is_some_and(|max| entry.size > max)
+        {
+            return false;
+        }
+
+        let age = now.saturating_sub(entry.modified);
+        if self.newer_than.is_some_and(|limit| age > limit)
+            || self.older_than.is_some_and(|limit| age < limit)
+        {
+            return false;
+        }
+
+        if self.extensions.is_empty() && self.file_types.is_empty() {
+            return true;
+        }
+        // Compared as bytes, so a name that is not valid UTF-8 is still matched by its extension
+        let Some(extension) = path
+            .extension()
+            .map(|extension| extension.as_bytes().to_ascii_lowercase())
+        else {
+            return false;
+        };
+
+        self.extensions.iter().any(|wanted| {
+            wanted
+                .trim_start_matches('.')
+                .as_bytes()
+                .eq_ignore_ascii_case(&extension)
+        }) || self.file_types.iter().any(|file_type| {
+            file_type
+                .extensions()
+                .iter()
+                .any(|known| known.as_bytes() == extension)
+        })
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::{Error, Result};
+use std::{
+    fs::{File, Metadata, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    os::unix::{fs::MetadataExt, io::AsRawFd},
+    path::Path,
+};
+
+/// Size of the pieces that are checked for zeros before they are written.
+const HOLE_SIZE: usize = 4096;
+const READ_SIZE: usize = 1024 * 1024;
+
+/// Checks whether a file has fewer blocks on the disk than its size needs.
+pub fn is_sparse(meta: &Metadata) -> bool {
+    meta.blocks() * 512 < meta.len()
+}
+
+/// Moves to the next data or hole from the offset, with `SEEK_DATA` or `SEEK_HOLE`.
+///
+/// Returns `None` when there is no more data after the offset.
+fn seek_to(file: &File, offset: u64, whence: i32) -> io::Result<Option<u64>> {
+    // SAFETY: the descriptor belongs to the open file for the whole call
+    let found = unsafe { libc::lseek(file.as_raw_fd(), offset as libc::off_t, whence) };
+    if found >= 0 {
+        return Ok(Some(found as u64));
+    }
+
+    let error = io::Error::last_os_error();
+    match error.raw_os_error() {
+        Some(libc::ENXIO) => Ok(None),
+        _ => Err(error),
+    }
+}
+
+/// Gives the regions of a file that hold data, as their offset and length.
+///
+/// A file system without `SEEK_DATA` support gives the whole file as one region.
+pub fn data_regions(file: &File, len: u64) -> io::Result<Vec<(u64, u64)>> {
+    let mut regions = Vec::new();
+    let mut offset = 0;
+
+    while offset < len {
+        let start = match seek_to(file, offset, libc::SEEK_DATA) {
+            Ok(Some(start)) => start,
+            Ok(None) => break,
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(vec![(0, len)]),
+            Err(e) => return Err(e),
+        };
+        let end = seek_to(file, start, libc::SEEK_HOLE)?
+            .unwrap_or(len)
+            .min(len);
+        regions.push((start, end - start));
+        offset = end;
+    }
+    Ok(regions)
+}
+
+/// Frees the blocks of a range so it reads as zeros, or writes the zeros when the
+/// file system can not punch holes.
+fn punch_hole(file: &mut File, offset: u64, len: usize) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: the descriptor belongs to the open file for the whole call
+        let punched = unsafe {
+            libc::fallocate(
+                file.as_raw_fd(),
+                libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                offset as libc::off_t,
+                len as libc::off_t,
+            )
+        };
+        if punched == 0 {
+            return Ok(());
+        }
+    }
+
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(&vec![0u8; len])
+}
+
+/// Writes data at an offset and leaves holes where it has whole pieces of zeros.
+///
+/// Takes:
+/// - File that is written
+/// - Path of the file for the errors
+/// - Offset of the data in the file
+/// - Data to write
+///
+/// Returns:
+/// - Error if the file can not be written
+pub fn write_sparse(file: &mut File, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+    // The pieces are aligned to the file, so a hole always covers whole blocks
+    let piece_end = |from: usize| {
+        let into_block = ((offset + from as u64) % HOLE_SIZE as u64) as usize;
+        (from + HOLE_SIZE - into_block).min(data.len())
+    };
+    let is_hole =
+        |from: usize, to: usize| to - from == HOLE_SIZE && data[from..to].iter().all(|b| *b == 0);
+
+    let mut start = 0;
+    while start < data.len() {
+        let mut end = piece_end(start);
+        let hole = is_hole(start, end);
+
+        // Neighbouring pieces of the same kind are handled together
+        while end < data.len() {
+            let next = piece_end(end);
+            if is_hole(end, next) != hole {
+                break;
+            }
+            end = next;
+        }
+
+        let at = offset + start as u64;
+        let written = if hole {
+            punch_hole(file, at, end - start)
+        } else {
+            file.seek(SeekFrom::Start(at))
+                .and_then(|_| file.write_all(&data[start..end]))
+        };
+        written.map_err(Error::io(path))?;
+        start = end;
+    }
+    Ok(())
+}
+
+/// Punches the holes of the source into the same ranges of the destination.
+///
+/// Takes:
+/// - Source file and its path
+/// - Destination file and its path, already as long as the source
+/// - Size of the source
+///
+/// Returns:
+/// - Error if the regions of the source can not be read or the destination written
+pub fn copy_holes(
+    src: &File,
+    src_path: &Path,
+    dest: &mut File,
+    dest_path: &Path,
+    len: u64,
+) -> Result<()> {
+    let regions = data_regions(src, len).map_err(Error::io(src_path))?;
+    let block = HOLE_SIZE as u64;
+
+    let mut hole_start: u64 = 0;
+    for (start, region_len) in regions.into_iter().chain([(len, 0)]) {
+        // Only the whole blocks inside the hole are freed, the last one may end the file
+        let from = hole_start.div_ceil(block) * block;
+        let to = if start == len {
+            len
+        } else {
+            start / block * block
+        };
+        if to > from {
+            punch_hole(dest, from, (to - from) as usize).map_err(Error::io(dest_path))?;
+        }
+        hole_start = start + region_len;
+    }
+    Ok(())
+}
+
+/// Copies a file and keeps its holes, only the regions that hold data are read and written.
+///
+/// Takes:
+/// - Source file
+/// - Destination file, created or truncated
+///
+/// Returns:
+/// - Error if any of the files can not be read or written
+pub fn copy_sparse(src_path: &Path, dest_path: &Path) -> Result<()> {
+    let mut src = File::open(src_path).map_err(Error::io(src_path))?;
+    let len = src.metadata().map_err(Error::io(src_path))?.len();
+    let mut dest = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dest_path)
+        .map_err(Error::io(dest_path))?;
+
+    let regions = data_regions(&src, len).map_err(Error::io(src_path))?;
+    let mut buf = vec![0u8; READ_SIZE];
+    for (start, region_len) in regions {
+        src.seek(SeekFrom::Start(start))
+            .map_err(Error::io(src_path))?;
+        dest.seek(SeekFrom::Start(start))
+            .map_err(Error::io(dest_path))?;
+
+        let mut left = region_len;
+        while left > 0 {
+            let chunk = (left as usize).min(READ_SIZE);
+            src.read_exact(&mut buf[..chunk])
+                .map_err(Error::io(src_path))?;
+            dest.write_all(&buf[..chunk])
+                .map_err(Error::io(dest_path))?;
+            left -= chunk as u64;
+        }
+    }
+
+    // The tail after the last region stays a hole
+    dest.set_len(len).map_err(Error::io(dest_path))
+}
@@ -0,0 +1,168 @@
+use super::{archive::is_root, index::TreeIndex, named::named_values, sync::SyncData};
+use crate::error::{Error, Result};
+use std::{
+    ffi::CString,
+    fs::{FileType, Metadata, Permissions, set_permissions},
+    io,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+/// Points to the way FIFOs, sockets and device nodes in the source are synced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialPolicy {
+    /// Make them again in the destination, device nodes only when running as root
+    Recreate,
+    /// Leave them out and report every one of them
+    #[default]
+    Skip,
+    /// Stop the sync at the first one, or report it as a failure while continuing on error
+    Fail,
+}
+
+named_values!(SpecialPolicy, "special file policy", {
+    Recreate => "recreate",
+    Skip => "skip",
+    Fail => "fail",
+});
+
+/// Gives the name of a special file type, `None` for regular files, directories and links.
+pub fn special_kind(file_type: &FileType) -> Option<&'static str> {
+    if file_type.is_fifo() {
+        Some("fifo")
+    } else if file_type.is_socket() {
+        Some("socket")
+    } else if file_type.is_char_device() {
+        Some("character device")
+    } else if file_type.is_block_device() {
+        Some("block device")
+    } else {
+        None
+    }
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path holds a nul byte"))
+}
+
+/// Makes a FIFO, socket or device node with the mode and device number of another one.
+pub(crate) fn make_special(path: &Path, mode: u32, rdev: u64) -> io::Result<()> {
+    let name = c_path(path)?;
+    // SAFETY: the name is a valid nul terminated string for the whole call
+    let made = unsafe { libc::mknod(name.as_ptr(), mode as libc::mode_t, rdev as libc::dev_t) };
+    if made != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // The umask is left out of the permissions
+    set_permissions(path, Permissions::from_mode(mode & 0o7777))
+}
+
+/// Sets the access and modification times of a path without opening it, since opening
+/// a FIFO or device has side effects. A link gets the times itself, it is not followed.
+pub(crate) fn set_path_times(path: &Path, meta: &Metadata) -> io::Result<()> {
+    let name = c_path(path)?;
+    let times = [
+        libc::timespec {
+            tv_sec: meta.atime() as libc::time_t,
+            tv_nsec: meta.atime_nsec() as _,
+        },
+        libc::timespec {
+            tv_sec: meta.mtime() as libc::time_t,
+            tv_nsec: meta.mtime_nsec() as _,
+        },
+    ];
+    // SAFETY: the name and times are valid for the whole call
+    let set = unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            name.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    };
+    if set != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Implementation for handling FIFOs, sockets and device nodes while scanning.
+impl SyncData {
+    /// Decides whether a special file found by the scan is indexed.
+    ///
+    /// Takes:
+    /// - Index that is built
+    /// - Relative path of the special file
+    /// - Metadata of the special file
+    /// - Whether the index is of the source
+    ///
+    /// Returns:
+    /// - Boolean to show the file is indexed, otherwise it is left alone
+    /// - Error if the policy is to fail
+    ///
+    /// A skipped source file is added to the skipped paths of the index with the reason.
+    /// The special files of the destination are only indexed when they are recreated,
+    /// so nothing the policy leaves out is removed.
+    pub(crate) fn index_special(
+        &self,
+        index: &mut TreeIndex,
+        relative: &Path,
+        meta: &Metadata,
+        is_source: bool,
+    ) -> Result<bool> {
+        let kind = special_kind(&meta.file_type()).unwrap_or("special file");
+        let absolute = index.absolute(relative);
+
+        if !is_source {
+            if self.specials != SpecialPolicy::Recreate {
+                index.excluded.insert(relative.to_path_buf());
+                return Ok(false);
+            }
+            index
+                .specials
+                .insert(relative.to_path_buf(), (meta.mode(), meta.rdev()));
+            return Ok(true);
+        }
+
+        let device = meta.file_type().is_char_device() || meta.file_type().is_block_device();
+        let reason = match self.specials {
+            SpecialPolicy::Fail => {
+                return Err(Error::SpecialFile {
+                    path: absolute,
+                    kind,
+                });
+            }
+            SpecialPolicy::Recreate if device && !is_root() => {
+                format!("{} is not recreated without root", kind)
+            }
+            SpecialPolicy::Recreate => {
+                index
+                    .specials
+                    .insert(relative.to_path_buf(), (meta.mode(), meta.rdev()));
+                return Ok(true);
+            }
+            SpecialPolicy::Skip => format!("{} is skipped", kind),
+        };
+
+        index.excluded.insert(relative.to_path_buf());
+        index.skipped.push((absolute, reason));
+        Ok(false)
+    }
+}
+
+/// Gives the paths of the special files that differ between the sides, in their order.
+pub(crate) fn changed_specials<'a>(
+    source: &'a TreeIndex,
+    destination: &TreeIndex,
+) -> Vec<&'a PathBuf> {
+    source
+        .specials
+        .iter()
+        .filter(|(path, special)| destination.specials.get(*path) != Some(special))
+        .map(|(path, _)| path)
+        .collect()
+}
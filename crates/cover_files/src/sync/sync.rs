@@ -1,6 +1,25 @@
-use std::path::PathBuf;
+use super::{
+    changes::finish_query,
+    checksum::CHECKSUM_ALGORITHM,
+    collision::print_collisions,
+    exclude::FilterRule,
+    guard::{indexed_removals, planned_removals, replaced_entries},
+    index::SyncIndex,
+    links::LinkPolicy,
+    mirror::DeleteTiming,
+    plan::{OperationKind, SyncPlan},
+    report::SyncReport,
+    select::FileType,
+    special::SpecialPolicy,
+    target::TargetFs,
+};
+use crate::error::{Error, Result};
+use std::{path::PathBuf, time::Duration};
 
 /// Requires the data for generating the output after running the commands.
+///
+/// The options that are not given fall back to their default with `..Default::default()`.
+#[derive(Debug, Default)]
 pub struct SyncData {
     pub source: PathBuf,
     pub destination: PathBuf,
@@ -8,11 +27,66 @@ pub struct SyncData {
     pub delete: bool,
     pub dry_run: bool,
     pub verbose: bool,
+    /// Keep syncing the rest of the tree when a path fails
+    pub continue_on_error: bool,
+    /// Timestamps within this window of each other are treated as equal
+    pub modify_window: Duration,
+    /// Compare the size and checksum of every file pair, whatever the timestamps
+    pub checksum: bool,
+    /// Number of threads that compare, hash and copy the files, zero or one runs on one thread
+    pub jobs: usize,
+    /// Always copy modified files whole instead of updating their changed blocks
+    pub whole_file: bool,
+    /// Update modified files above the delta threshold in place instead of through a temporary sibling
+    pub inplace: bool,
+    /// Size in bytes above which modified files are updated with a delta, 8 MiB when not given
+    pub delta_threshold: Option<u64>,
+    /// Include and exclude patterns, the first one that matches a path decides
+    pub filters: Vec<FilterRule>,
+    /// Leave out the paths git ignores with its `.gitignore`, `info/exclude` and global excludes files
+    pub respect_gitignore: bool,
+    /// Leave out the `.git` directories
+    pub exclude_git_dir: bool,
+    /// Sync only the files of at least this many bytes
+    pub min_size: Option<u64>,
+    /// Sync only the files of at most this many bytes
+    pub max_size: Option<u64>,
+    /// Sync only the files modified within this long before the run
+    pub newer_than: Option<Duration>,
+    /// Sync only the files modified at least this long before the run
+    pub older_than: Option<Duration>,
+    /// Sync only the files with one of these extensions, or of one of the `file_types`
+    pub extensions: Vec<String>,
+    /// Sync only the files of one of these types, or with one of the `extensions`
+    pub file_types: Vec<FileType>,
+    /// Way the symbolic links of the source are synced, they are followed by default
+    pub links: LinkPolicy,
+    /// Keep the owner when running as root, the ACLs, extended attributes and directory metadata
+    pub archive: bool,
+    /// Recreate the hard links between source files in the destination
+    pub hard_links: bool,
+    /// Way the FIFOs, sockets and device nodes of the source are synced, they are skipped by default
+    pub specials: SpecialPolicy,
+    /// Stay on the file system of the source and destination, the mount points under them are left out
+    pub one_file_system: bool,
+    /// Restrictive file system the destination is on, the names it rejects are mapped reversibly
+    pub target_fs: Option<TargetFs>,
+    /// Copy and update every file, then remove the destination entries the source lacks
+    pub mirror: bool,
+    /// Moment the extra destination entries are removed while mirroring, after the copies by default
+    pub delete_timing: DeleteTiming,
+    /// Stop before removing anything when more than this many destination entries would be removed
+    pub max_delete: Option<usize>,
+    /// Stop before removing anything when more than this percent of the destination entries would be removed
+    pub max_delete_percent: Option<f64>,
+    /// Ask on the terminal before removing destination entries, when the standard input is one
+    pub confirm_removals: bool,
 }
 
-/// Points to the states that needs to be present.
+/// Points to the states that are detected between the source and destination.
 ///
-/// Only one action is required to be hit at the time.
+/// Every state that is hit is reconciled in the same run.
+#[derive(Debug, PartialEq)]
 pub enum FileState {
     /// Checks if the source files are created
     SrcCreated,
@@ -32,10 +106,12 @@ pub enum FileAction {
     ChangedOnly,
     /// Show detailed logs
     Verbose,
-    /// Show what would happen after syncing
+    /// Show the planned operations without applying them
     DryRun,
     /// Remove files in destination not in source
     Delete,
+    /// Make the destination an exact copy of the source
+    Mirror,
 }
 
 /// Implementation for the output that will be generated after running the command.
@@ -49,141 +125,333 @@ impl SyncData {
             FileAction::DryRun
         } else if self.delete {
             FileAction::Delete
+        } else if self.mirror {
+            FileAction::Mirror
         } else {
             FileAction::Verbose
         }
     }
 
-    fn to_state(&self, condition: &mut [bool]) -> FileState {
+    /// Handles the result of a single path.
+    ///
+    /// Gives back the value when it succeeds. When it fails, the error is returned,
+    /// or pushed to the failures if the sync continues on error.
+    pub(crate) fn recover<T>(
+        &self,
+        result: Result<T>,
+        failures: &mut Vec<Error>,
+    ) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.continue_on_error => {
+                failures.push(e);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn to_state(&self, condition: &[bool; 4]) -> Vec<FileState> {
+        let mut states = Vec::new();
+
         if condition[0] {
-            FileState::SrcCreated
-        } else if condition[1] {
-            FileState::SrcModified
-        } else if condition[2] {
-            FileState::DestCreated
-        } else if condition[3] {
-            FileState::DestModified
-        } else {
-            FileState::NoChange
+            states.push(FileState::SrcCreated);
+        }
+        if condition[1] {
+            states.push(FileState::SrcModified);
+        }
+        if condition[2] {
+            states.push(FileState::DestCreated);
         }
+        if condition[3] {
+            states.push(FileState::DestModified);
+        }
+
+        if states.is_empty() {
+            states.push(FileState::NoChange);
+        }
+        states
+    }
+
+    /// Detects every kind of difference between the source and destination.
+    ///
+    /// Returns:
+    /// - Files modified in the source
+    /// - Files modified in the destination
+    /// - All the states that are hit, in the order they need to be reconciled
+    /// - Error if a path fails, `Error::Incomplete` when it fails while continuing on error
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::{FileState, SyncData};
+    /// use std::path::PathBuf;
+    ///
+    /// let sync = SyncData {
+    ///     source: PathBuf::from("source_directory"),
+    ///     destination: PathBuf::from("destination_directory"),
+    ///     changed_only: true,
+    ///     delete: false,
+    ///     dry_run: false,
+    ///     verbose: false,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let (_, _, states) = sync.file_status().expect("[ERROR]: failed to get the status");
+    /// assert!(!states.contains(&FileState::NoChange));
+    /// ```
+    pub fn file_status(&self) -> Result<(Vec<PathBuf>, Vec<PathBuf>, Vec<FileState>)> {
+        let mut index = self.scan()?;
+        let mut failures = std::mem::take(&mut index.failures);
+
+        let src_created = self.src_created_in(&index);
+        let (modified_src_file, src_modified) = self.src_modified_in(&index, &mut failures)?;
+
+        let dest_created = self.dest_created_in(&index);
+        let (modified_dest_file, dest_modified) = self.dest_modified_in(&index, &mut failures)?;
+
+        let condition: [bool; 4] = [src_created, src_modified, dest_created, dest_modified];
+        let states = self.to_state(&condition);
+
+        finish_query((modified_src_file, modified_dest_file, states), failures)
     }
 
-    fn file_status(&self) -> (Vec<PathBuf>, Vec<PathBuf>, FileState) {
-        let src_created = self.src_file_created();
-        let (modified_src_file, src_modified) = self.src_file_modified();
+    /// Gives the same states as `file_status` from a plan that is already built.
+    ///
+    /// The updated files are split by the side that has the newer timestamp,
+    /// so no file is compared again.
+    fn plan_status(
+        &self,
+        index: &SyncIndex,
+        plan: &SyncPlan,
+    ) -> (Vec<PathBuf>, Vec<PathBuf>, Vec<FileState>) {
+        let mut modified_src_file = Vec::new();
+        let mut modified_dest_file = Vec::new();
+
+        for op in plan.iter().filter(|op| op.kind == OperationKind::Update) {
+            let src_time = index.source.get(&op.path).map(|entry| entry.modified);
+            let dest_time = index.destination.get(&op.path).map(|entry| entry.modified);
 
-        let dest_created = self.dest_file_created();
-        let (modified_dest_file, dest_modified) = self.dest_file_modified();
+            match (src_time, dest_time) {
+                (Some(src), Some(dest)) if self.is_newer(dest, src) => {
+                    modified_dest_file.push(op.path.clone())
+                }
+                _ => modified_src_file.push(op.path.clone()),
+            }
+        }
 
-        let mut condition: [bool; 4] = [src_created, src_modified, dest_created, dest_modified];
-        let state = self.to_state(&mut condition);
+        let condition: [bool; 4] = [
+            plan.contains(OperationKind::Replace)
+                || plan.contains(OperationKind::Mkdir)
+                || plan.contains(OperationKind::Move)
+                || plan.contains(OperationKind::Create)
+                || plan.contains(OperationKind::Link)
+                || plan.contains(OperationKind::Mknod),
+            !modified_src_file.is_empty(),
+            plan.contains(OperationKind::Delete) || plan.contains(OperationKind::Rmdir),
+            !modified_dest_file.is_empty(),
+        ];
+        let states = self.to_state(&condition);
 
-        (modified_src_file, modified_dest_file, state)
+        (modified_src_file, modified_dest_file, states)
     }
 
     /// Runs the sync operation between the source and destination.
     ///
     /// This function validates the selected options and executes the
-    /// suitble sync action (changed-only, verbose, dry-run, or delete).
-    /// Any errors or status messages are printed to standard output.
-    pub fn sync_output(&mut self) {
+    /// suitble sync action (changed-only, verbose, dry-run, delete, or mirror).
+    /// All the detected states are reconciled in a single run, so new,
+    /// modified and extra files are handled together.
+    /// The changed-only action applies the sync plan and the dry-run action prints it.
+    /// The mirror action applies it with the removals at the chosen delete timing.
+    ///
+    /// Returns:
+    /// - Error if the options are invalid or a path fails to sync
+    /// - Error if more destination entries would be removed than the limits allow, or the
+    ///   removal is not confirmed on the terminal, before any entry is removed
+    ///
+    /// When the sync continues on error, the failed paths are printed at the end
+    /// and `Error::Incomplete` is returned with their count.
+    pub fn sync_output(&mut self) -> Result<()> {
         if !self.src_dest_dir_present() {
-            eprintln!("[ERROR]: missing source or destination directories");
-            return;
+            return Err(Error::MissingDirectory);
         }
 
         if !self.single_command_selected() {
-            eprintln!(
-                "[ERROR]: no or multiple option(s) are selected. See 'cargo run sync --help'"
-            );
-            return;
+            return Err(Error::InvalidOptions);
         }
 
-        if self.has_duplicates() {
-            eprintln!("[ERROR]: duplicate files and directories are not allowed");
-            return;
-        }
+        let mut report = SyncReport::default();
+        let mut index = self.scan()?;
+        report.failures.append(&mut index.failures);
+        report.skipped.append(&mut index.source.skipped);
+        report.skipped.append(&mut index.destination.skipped);
 
         let action = self.to_action();
+        // Removing the destination writes no names, so nothing can collide
+        if !matches!(action, FileAction::Delete) {
+            let collisions = self.dest_collisions(&index)?;
+            if !collisions.is_empty() {
+                print_collisions(&index.source, &collisions);
+                return Err(Error::NameCollisions(collisions.len()));
+            }
+        }
+
         match action {
             FileAction::ChangedOnly => {
-                let (modified_src_file, modified_dest_file, state) = self.file_status();
-                match state {
-                    FileState::SrcCreated => {
-                        self.copy_src_to_dest();
-                        println!("[SUCCESS]: successfully copied source file(s)");
-                    }
-                    FileState::SrcModified => {
-                        self.update_dest_file(modified_src_file);
-                        println!("[SUCCESS]: successfully updated destination file(s)");
-                    }
-                    FileState::DestCreated => {
-                        self.remove_dest_file();
-                        println!(
-                            "[MESSAGE]: extra file(s) are not allowed in the destination. They are removed"
-                        );
-                    }
-                    FileState::DestModified => {
-                        self.update_dest_file(modified_dest_file);
-                        println!(
-                            "[MESSAGE]: file modification is not allowed in the destination. They are overwritten"
-                        )
-                    }
-                    FileState::NoChange => {
-                        println!("[STATUS]: no changes detected");
-                    }
+                let mut plan = self.plan_from_index(&index)?;
+                report.failures.append(&mut plan.failures);
+
+                if plan.is_empty() && report.is_success() {
+                    report.print_skipped();
+                    println!("[STATUS]: no changes detected");
+                    return Ok(());
+                }
+
+                self.guard_removals(
+                    planned_removals(&plan, &index.destination),
+                    index.destination.entries.len(),
+                )?;
+                report.merge(self.apply_plan(&plan)?);
+
+                if plan.contains(OperationKind::Replace) {
+                    println!(
+                        "[MESSAGE]: destination entries of another type than the source are replaced"
+                    );
+                }
+                if plan.contains(OperationKind::Move) {
+                    println!("[SUCCESS]: successfully moved renamed file(s) in the destination");
+                }
+                if plan.contains(OperationKind::Create) {
+                    println!("[SUCCESS]: successfully copied source file(s)");
+                }
+                if plan.contains(OperationKind::Update) {
+                    println!("[SUCCESS]: successfully updated destination file(s)");
+                }
+                if plan.contains(OperationKind::Mknod) {
+                    println!("[SUCCESS]: successfully recreated special file(s)");
+                }
+                if plan.contains(OperationKind::HardLink) {
+                    println!(
+                        "[SUCCESS]: successfully linked hard link group(s) in the destination"
+                    );
+                }
+                if plan.contains(OperationKind::Delete) || plan.contains(OperationKind::Rmdir) {
+                    println!(
+                        "[MESSAGE]: extra file(s) are not allowed in the destination. They are removed"
+                    );
+                }
+                if plan.contains(OperationKind::Attrs) {
+                    println!("[SUCCESS]: successfully copied the metadata of source entries");
                 }
             }
             FileAction::Verbose => {
-                let (modified_src_file, modified_dest_file, state) = self.file_status();
-                match state {
-                    FileState::SrcCreated => {
-                        self.src_creation_log();
-                    }
-                    FileState::SrcModified => {
-                        self.src_modification_log(modified_src_file);
-                    }
-                    FileState::DestCreated => {
-                        self.dest_creation_log();
-                    }
-                    FileState::DestModified => {
-                        self.dest_modification_log(modified_dest_file);
-                    }
-                    FileState::NoChange => {
-                        println!("[STATUS]: no changes detected");
+                self.mount_point_log(&index);
+                if self.checksum {
+                    println!("[CHECKSUM]: comparing files with {}", CHECKSUM_ALGORITHM);
+                }
+
+                let mut plan = self.plan_from_index(&index)?;
+                report.failures.append(&mut plan.failures);
+                self.guard_removals(
+                    planned_removals(&plan, &index.destination),
+                    index.destination.entries.len(),
+                )?;
+
+                let (modified_src_file, modified_dest_file, states) =
+                    self.plan_status(&index, &plan);
+                for state in states {
+                    match state {
+                        FileState::SrcCreated => {
+                            report.merge(self.src_creation_log(&index, &plan)?);
+                        }
+                        FileState::SrcModified => {
+                            report.merge(self.src_modification_log(
+                                &index,
+                                &plan,
+                                modified_src_file.clone(),
+                            )?);
+                        }
+                        FileState::DestCreated => {
+                            report.merge(self.dest_creation_log(&index, &plan)?);
+                        }
+                        FileState::DestModified => {
+                            report.merge(self.dest_modification_log(
+                                &index,
+                                &plan,
+                                modified_dest_file.clone(),
+                            )?);
+                        }
+                        FileState::NoChange => {
+                            if !plan.contains(OperationKind::HardLink)
+                                && !plan.contains(OperationKind::Attrs)
+                            {
+                                println!("[STATUS]: no changes detected");
+                            }
+                        }
                     }
                 }
+                if plan.contains(OperationKind::HardLink) {
+                    report.merge(self.hard_link_log(&plan)?);
+                }
+                if plan.contains(OperationKind::Attrs) {
+                    report.merge(self.attribute_log(&plan)?);
+                }
             }
             FileAction::DryRun => {
-                let (_, _, state) = self.file_status();
-                match state {
-                    FileState::SrcCreated => {
-                        println!("[DRY RUN]: will copy the source file(s) to destination");
-                    }
-                    FileState::SrcModified => {
-                        println!("[DRY RUN]: will update the destination file(s)");
-                    }
-                    FileState::DestCreated => {
-                        println!("[DRY RUN]: will prevent the creation of destination file(s)");
-                    }
-                    FileState::DestModified => {
-                        println!(
-                            "[DRY RUN]: will overwrite the destination file(s) with source file(s)"
-                        );
-                    }
-                    FileState::NoChange => {
-                        eprintln!("[DRY RUN]: will give the 'no changes detected' message");
-                    }
-                }
+                let mut plan = self.plan_from_index(&index)?;
+                report.failures.append(&mut plan.failures);
+                self.print_plan(&plan);
             }
             FileAction::Delete => {
-                self.remove_all_dest_files();
+                let removals = indexed_removals(&index.destination);
+                self.guard_removals(removals, index.destination.entries.len())?;
+                report.merge(self.remove_indexed_dest_files(&index.destination)?);
             }
+            FileAction::Mirror => {
+                let mut plan = self.plan_from_index(&index)?;
+                report.failures.append(&mut plan.failures);
+
+                if plan.is_empty() && report.is_success() {
+                    report.print_skipped();
+                    println!("[STATUS]: no changes detected");
+                    return Ok(());
+                }
+
+                // With the `after` timing the question is asked for the removals of the new scan,
+                // and only the replaced entries are confirmed before the copies
+                let removals = planned_removals(&plan, &index.destination);
+                match self.delete_timing {
+                    DeleteTiming::After => {
+                        self.check_delete_limits(removals, index.destination.entries.len())?;
+                        self.ask_to_remove(replaced_entries(&plan, &index.destination))?
+                    }
+                    _ => self.guard_removals(removals, index.destination.entries.len())?,
+                }
+                let scanned = report.is_success();
+                report.merge(self.apply_mirror(&plan, scanned)?);
+                println!(
+                    "[SUCCESS]: successfully mirrored the source with the extra file(s) removed {} the copies",
+                    match self.delete_timing {
+                        DeleteTiming::Before => "before",
+                        DeleteTiming::After | DeleteTiming::Delay => "after",
+                    }
+                );
+            }
+        }
+
+        report.print_skipped();
+        report.print_delta();
+
+        if !report.is_success() {
+            report.print_failures();
+            return Err(Error::Incomplete(report.failures.len()));
         }
+        Ok(())
     }
 
     /// Executes the selected sync option.
-    pub fn sync_options(&mut self) {
-        self.sync_output();
+    pub fn sync_options(&mut self) -> Result<()> {
+        self.sync_output()
     }
 }
@@ -0,0 +1,352 @@
+use super::{index::TreeIndex, named::named_values, sync::SyncData};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::{OsStr, OsString},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+/// Longest name in bytes that is written, the limit of the file systems is 255 UTF-16 units
+/// and no character takes more bytes than units.
+const MAX_NAME_BYTES: usize = 255;
+
+/// Names that Windows keeps for devices, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters the file systems reject, in the order of their private use characters from `U+F020`.
+const FORBIDDEN: [char; 8] = ['"', '*', ':', '<', '>', '?', '\\', '|'];
+
+/// Private use character of a trailing space, `U+F029` is the one of a trailing dot.
+const TRAILING_SPACE: u32 = 0xF028;
+/// Private use characters from here keep the first ASCII character of a reserved name.
+const RESERVED_START: u32 = 0xF100;
+/// Private use characters from here keep the bytes of a name that is not valid UTF-8.
+const BYTE_START: u32 = 0xF200;
+
+/// Points to the restrictive file system the destination is on.
+///
+/// The three share the rules of Windows names, so a name any of them rejects is mapped
+/// the same way. They differ in how precise their timestamps are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetFs {
+    /// FAT32 and exFAT, like most USB drives and SD cards
+    Fat,
+    /// NTFS, like Windows drives
+    Ntfs,
+    /// SMB and CIFS network shares
+    Smb,
+}
+
+named_values!(TargetFs, "target file system", {
+    Fat => "fat",
+    Ntfs => "ntfs",
+    Smb => "smb",
+});
+
+impl TargetFs {
+    /// Gives the steps the file system keeps the modification times in.
+    pub fn time_resolution(self) -> Duration {
+        match self {
+            TargetFs::Fat => Duration::from_secs(2),
+            TargetFs::Ntfs | TargetFs::Smb => Duration::from_nanos(100),
+        }
+    }
+}
+
+/// Gives the private use character a character of a name is written as, if it needs one.
+fn forbidden_char(c: char) -> Option<char> {
+    let code = match c {
+        '\u{1}'..='\u{1f}' => 0xF000 + c as u32,
+        c => 0xF020 + FORBIDDEN.iter().position(|forbidden| *forbidden == c)? as u32,
+    };
+    char::from_u32(code)
+}
+
+/// Checks whether the part of a name before its first dot is a reserved device name.
+fn is_reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Shortens a name that is too long and keeps it apart with the hash of the original name.
+fn shorten(name: String, original: &[u8]) -> String {
+    let hash = blake3::hash(original).to_hex();
+    let extension = name
+        .rfind('.')
+        .map(|dot| &name[dot..])
+        .filter(|extension| extension.len() <= 16)
+        .unwrap_or_default();
+    let suffix = format!("~{}{}", &hash[..16], extension);
+
+    let mut short = String::new();
+    for c in name.chars() {
+        if short.len() + c.len_utf8() + suffix.len() > MAX_NAME_BYTES {
+            break;
+        }
+        short.push(c);
+    }
+    short + &suffix
+}
+
+/// Maps one name to a name every target file system takes.
+///
+/// Takes:
+/// - Name of a file or directory in the source
+///
+/// Returns:
+/// - Name for the destination, the same name when nothing has to change
+///
+/// Control characters and `" * : < > ? \ |` become the private use characters from
+/// `U+F001` that Samba and macOS use for SMB, and so do trailing spaces (`U+F028`) and
+/// dots (`U+F029`). The first character of a reserved name like `CON` or `nul.txt` is moved
+/// to `U+F100` and up, and every byte of a name that is not valid UTF-8 to `U+F200` and up.
+/// A name that is still longer than 255 bytes is cut and ends with a hash of the original.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cover_files::sync::target::{decode_name, encode_name};
+/// use std::ffi::OsStr;
+///
+/// let encoded = encode_name(OsStr::new("notes: draft?.txt"));
+/// assert_eq!(encoded, "notes\u{f022} draft\u{f025}.txt");
+/// assert_eq!(decode_name(&encoded), "notes: draft?.txt");
+/// ```
+pub fn encode_name(name: &OsStr) -> OsString {
+    let bytes = name.as_bytes();
+    let mut chars: Vec<char> = Vec::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        chars.extend(
+            chunk
+                .valid()
+                .chars()
+                .map(|c| forbidden_char(c).unwrap_or(c)),
+        );
+        chars.extend(
+            chunk
+                .invalid()
+                .iter()
+                .filter_map(|byte| char::from_u32(BYTE_START + *byte as u32)),
+        );
+    }
+
+    for c in chars.iter_mut().rev() {
+        match c {
+            ' ' => *c = char::from_u32(TRAILING_SPACE).unwrap_or(*c),
+            '.' => *c = char::from_u32(TRAILING_SPACE + 1).unwrap_or(*c),
+            _ => break,
+        }
+    }
+
+    let mut encoded: String = chars.into_iter().collect();
+    if is_reserved(&encoded) {
+        let first = encoded.remove(0);
+        encoded.insert(
+            0,
+            char::from_u32(RESERVED_START + first as u32).unwrap_or(first),
+        );
+    }
+    if encoded.len() > MAX_NAME_BYTES {
+        encoded = shorten(encoded, bytes);
+    }
+
+    if encoded.as_bytes() == bytes {
+        name.to_os_string()
+    } else {
+        encoded.into()
+    }
+}
+
+/// Gives back the source name of a name that is mapped by `encode_name`.
+///
+/// A name that is cut to fit keeps its hash, the index of the source finds it instead.
+pub fn decode_name(name: &OsStr) -> OsString {
+    let Some(name) = name.to_str() else {
+        return name.to_os_string();
+    };
+
+    let mut bytes = Vec::with_capacity(name.len());
+    for c in name.chars() {
+        let code = c as u32;
+        let original = match code {
+            0xF001..=0xF01F => Some(code - 0xF000),
+            0xF020..=0xF027 => Some(FORBIDDEN[(code - 0xF020) as usize] as u32),
+            TRAILING_SPACE => Some(' ' as u32),
+            0xF029 => Some('.' as u32),
+            _ if (RESERVED_START..RESERVED_START + 0x80).contains(&code) => {
+                Some(code - RESERVED_START)
+            }
+            _ if (BYTE_START + 0x80..BYTE_START + 0x100).contains(&code) => {
+                bytes.push((code - BYTE_START) as u8);
+                continue;
+            }
+            _ => None,
+        };
+        let c = original.and_then(char::from_u32).unwrap_or(c);
+        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    OsString::from_vec(bytes)
+}
+
+/// Maps every name of a relative path with `encode_name`.
+pub fn encode_path(path: &Path) -> PathBuf {
+    map_path(path, encode_name)
+}
+
+/// Maps every name of a relative path with `decode_name`.
+pub fn decode_path(path: &Path) -> PathBuf {
+    map_path(path, decode_name)
+}
+
+fn map_path(path: &Path, map: fn(&OsStr) -> OsString) -> PathBuf {
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => map(name),
+            other => other.as_os_str().to_os_string(),
+        })
+        .collect()
+}
+
+/// Moves the entry of a key to another key, in any of the maps of the index.
+fn rekey<T>(map: &mut BTreeMap<PathBuf, T>, from: &Path, to: &Path) {
+    if let Some(value) = map.remove(from) {
+        map.insert(to.to_path_buf(), value);
+    }
+}
+
+fn rekey_set(set: &mut BTreeSet<PathBuf>, from: &Path, to: &Path) {
+    if set.remove(from) {
+        set.insert(to.to_path_buf());
+    }
+}
+
+/// Implementation for syncing to a file system with restrictive names.
+impl SyncData {
+    /// Gives the path of a relative source path in the destination.
+    ///
+    /// Takes:
+    /// - Path relative to the source and destination
+    ///
+    /// Returns:
+    /// - Path in the destination, with its names mapped when a target file system is given
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::SyncData;
+    /// use cover_files::sync::target::TargetFs;
+    /// use std::path::{Path, PathBuf};
+    ///
+    /// let sync = SyncData {
+    ///     destination: PathBuf::from("/media/usb"),
+    ///     target_fs: Some(TargetFs::Fat),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let path = sync.dest_path(Path::new("logs/CON.txt"));
+    /// assert_eq!(path, PathBuf::from("/media/usb/logs/\u{f143}ON.txt"));
+    /// ```
+    pub fn dest_path(&self, path: &Path) -> PathBuf {
+        match self.target_fs {
+            Some(_) => self.destination.join(encode_path(path)),
+            None => self.destination.join(path),
+        }
+    }
+
+    /// Gives the path of an entry in the destination, with the names it already has on the disk.
+    ///
+    /// Takes:
+    /// - On-disk paths of the destination entries that are not at the mapped path of their key
+    /// - Path relative to the source and destination
+    ///
+    /// Returns:
+    /// - Path in the destination, through the nearest entry that is already on the disk,
+    ///   and mapped like `dest_path` for the rest
+    ///
+    /// A destination name like `CON` or `x:y` that was not written by the sync keeps its name,
+    /// so it is updated, moved and removed where it is.
+    pub(crate) fn dest_entry(&self, names: &BTreeMap<PathBuf, PathBuf>, path: &Path) -> PathBuf {
+        for ancestor in path.ancestors() {
+            let Some(on_disk) = names.get(ancestor) else {
+                continue;
+            };
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return match rest.as_os_str().is_empty() {
+                true => self.destination.join(on_disk),
+                false => self.destination.join(on_disk).join(encode_path(rest)),
+            };
+        }
+        self.dest_path(path)
+    }
+
+    /// Gives the on-disk paths of the destination entries that `dest_path` would not find.
+    ///
+    /// Takes:
+    /// - Index of the destination
+    ///
+    /// Returns:
+    /// - On-disk relative path of every entry whose name is not the mapped name of its key,
+    ///   empty without a target file system
+    pub(crate) fn dest_names(&self, destination: &TreeIndex) -> BTreeMap<PathBuf, PathBuf> {
+        if self.target_fs.is_none() {
+            return BTreeMap::new();
+        }
+
+        destination
+            .entries
+            .keys()
+            .filter_map(|path| {
+                let on_disk = destination.names.get(path).unwrap_or(path);
+                (*on_disk != encode_path(path)).then(|| (path.clone(), on_disk.clone()))
+            })
+            .collect()
+    }
+
+    /// Gives the destination paths that are cut to fit the keys of their source paths.
+    ///
+    /// Takes:
+    /// - Index of the source
+    /// - Index of the destination, with the names that can be decoded already decoded
+    ///
+    /// A cut name can not be decoded, so the source path that encodes to it is searched.
+    /// Every other decoded path is left as it is.
+    pub(crate) fn match_dest_names(&self, source: &TreeIndex, destination: &mut TreeIndex) {
+        if self.target_fs.is_none() {
+            return;
+        }
+
+        let keys = source
+            .entries
+            .keys()
+            .chain(source.filtered.keys())
+            .chain(&source.excluded);
+        for path in keys {
+            let decoded = decode_path(&encode_path(path));
+            if &decoded == path || destination.contains(path) {
+                continue;
+            }
+            if !destination.contains(&decoded) && !destination.excluded.contains(&decoded) {
+                continue;
+            }
+
+            let on_disk = destination
+                .names
+                .remove(&decoded)
+                .unwrap_or_else(|| decoded.clone());
+            destination.names.insert(path.clone(), on_disk);
+            rekey(&mut destination.entries, &decoded, path);
+            rekey(&mut destination.filtered, &decoded, path);
+            rekey(&mut destination.links, &decoded, path);
+            rekey(&mut destination.specials, &decoded, path);
+            rekey(&mut destination.inodes, &decoded, path);
+            rekey_set(&mut destination.excluded, &decoded, path);
+            rekey_set(&mut destination.mount_points, &decoded, path);
+        }
+    }
+}
@@ -1,5 +1,11 @@
 use super::sync::SyncData;
-use std::{collections::BTreeMap, fs::metadata, path::PathBuf, time::UNIX_EPOCH};
+use crate::error::{Error, Result};
+use std::{
+    collections::BTreeMap,
+    fs::metadata,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
 
 /// Implementation for finding the timestamp of a file.
 impl SyncData {
@@ -11,7 +17,8 @@ impl SyncData {
     ///
     /// Returns:
     /// - Actual file which is modified at the end
-    /// - Float timestamp of that file
+    /// - Timestamp of that file since the unix epoch, with nanosecond precision
+    /// - Error if a file can not be read or is not inside the trimmed directory
     ///
     /// # Example
     ///
@@ -26,43 +33,85 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
-    /// let src_files_list = sync.list_src_files();
-    /// let dest_files_list = sync.list_dest_files();
+    /// let src_files_list = sync.list_src_files().expect("[ERROR]: failed to list the files");
+    /// let dest_files_list = sync.list_dest_files().expect("[ERROR]: failed to list the files");
     ///
-    /// let src_timestamp = sync.file_timestamp(src_files_list.clone(), &sync.source);
-    /// let dest_timestamp = sync.file_timestamp(dest_files_list.clone(), &sync.destination);
+    /// let src_timestamp = sync
+    ///     .file_timestamp(src_files_list.clone(), &sync.source)
+    ///     .expect("[ERROR]: failed to get the timestamp");
+    /// let dest_timestamp = sync
+    ///     .file_timestamp(dest_files_list.clone(), &sync.destination)
+    ///     .expect("[ERROR]: failed to get the timestamp");
     ///
     /// for (path, dest_time) in &dest_timestamp {
     ///     let src = src_timestamp.get(path);
     ///     match src {
     ///         Some(src_time) => {
-    ///             assert!(*src_time != 0.0 && *dest_time != 0.0);
+    ///             assert!(!src_time.is_zero() && !dest_time.is_zero());
     ///         }
     ///         None => {}
     ///     }
     /// }
     /// ```
-    pub fn file_timestamp(&self, files: Vec<PathBuf>, trim: &PathBuf) -> BTreeMap<PathBuf, f64> {
-        let mut map: BTreeMap<PathBuf, f64> = BTreeMap::new();
+    pub fn file_timestamp(
+        &self,
+        files: Vec<PathBuf>,
+        trim: &PathBuf,
+    ) -> Result<BTreeMap<PathBuf, Duration>> {
+        let mut map: BTreeMap<PathBuf, Duration> = BTreeMap::new();
 
         for entry in files {
-            let num = metadata(&entry)
-                .ok()
-                .and_then(|f| f.modified().ok())
-                .and_then(|f| f.duration_since(UNIX_EPOCH).ok())
-                .map(|f| f.as_secs() as f64)
-                .unwrap_or(0.0);
+            let num = self.timestamp_of(&entry)?;
 
             let file = entry
-                .clone()
                 .strip_prefix(trim)
-                .expect("[ERROR]: failed to get the prefix")
+                .map_err(|_| Error::OutsideRoot {
+                    path: entry.clone(),
+                    root: trim.clone(),
+                })?
                 .to_path_buf();
 
             map.insert(file, num);
         }
-        map
+        Ok(map)
+    }
+
+    /// Gives the modification timestamp of a single file.
+    pub(crate) fn timestamp_of(&self, path: &Path) -> Result<Duration> {
+        let modified = metadata(path)
+            .and_then(|f| f.modified())
+            .map_err(Error::io(path))?;
+
+        Ok(modified.duration_since(UNIX_EPOCH).unwrap_or_default())
+    }
+
+    /// Checks whether the first timestamp is newer than the second one.
+    ///
+    /// Timestamps that are within the modify window of each other are treated as equal,
+    /// so destinations with a coarse timestamp granularity are not seen as modified.
+    /// A target file system widens the window to the steps it keeps the times in.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::time::Duration;
+    ///
+    /// let sync = SyncData {
+    ///     modify_window: Duration::from_secs(2),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert!(!sync.is_newer(Duration::from_secs(11), Duration::from_secs(10)));
+    /// assert!(sync.is_newer(Duration::from_secs(13), Duration::from_secs(10)));
+    /// ```
+    pub fn is_newer(&self, first: Duration, second: Duration) -> bool {
+        let window = self.target_fs.map_or(self.modify_window, |target| {
+            self.modify_window.max(target.time_resolution())
+        });
+        first > second && first - second > window
     }
 }
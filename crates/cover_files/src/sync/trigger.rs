@@ -1,17 +1,210 @@
-use super::sync::SyncData;
+use super::{
+    archive::{Attributes, copy_link_metadata},
+    index::TreeIndex,
+    links::LinkPolicy,
+    plan::{OperationKind, SyncOperation, SyncPlan},
+    report::SyncReport,
+    sparse::{copy_sparse, is_sparse},
+    special::{make_special, set_path_times},
+    sync::SyncData,
+};
+use crate::error::{Error, Result};
 use std::{
-    collections::HashSet,
-    fs::{copy, create_dir_all, remove_dir, remove_file},
-    path::PathBuf,
+    ffi::OsString,
+    fs::{
+        File, FileTimes, Metadata, copy, create_dir_all, hard_link, metadata, read_link,
+        remove_dir, remove_dir_all, remove_file, rename, set_permissions, symlink_metadata,
+    },
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{MetadataExt, symlink},
+    },
+    path::{Path, PathBuf},
+    process,
 };
 
+/// Longest name in bytes most file systems take.
+const MAX_NAME_LEN: usize = 255;
+
+/// Gives the temporary sibling that a file is written to before it is renamed into place.
+///
+/// The sibling is in the same directory, so the rename never crosses a file system.
+/// A name too long to take the suffix is replaced by its hash.
+fn temp_sibling(dest_path: &Path) -> PathBuf {
+    let name = dest_path.file_name().unwrap_or_default();
+    let suffix = format!(".{}.cover-tmp", process::id());
+
+    // The name is joined as it is, so two names that are not valid UTF-8 never share a sibling
+    let mut temp_name = OsString::from(".");
+    if name.len() + suffix.len() + 1 > MAX_NAME_LEN {
+        temp_name.push(&blake3::hash(name.as_bytes()).to_hex()[..32]);
+    } else {
+        temp_name.push(name);
+    }
+    temp_name.push(suffix);
+    dest_path.with_file_name(temp_name)
+}
+
+/// Copies the source file to the destination and keeps its timestamps and permissions.
+///
+/// The access and modification times are set on the copied file, so the next run can
+/// compare the timestamps instead of reading the content of both files.
+///
+/// The content is written to a temporary sibling and renamed over the destination,
+/// so a reader of the destination sees either the old or the new complete file.
+/// The sibling is removed when any step fails. In archive mode the owner, ACLs and
+/// extended attributes are set on the sibling as well, before it is renamed.
+fn copy_file(src_path: &Path, dest_path: &Path, archive: bool) -> Result<()> {
+    let temp_path = temp_sibling(dest_path);
+
+    let copied = write_temp(src_path, &temp_path)
+        .and_then(|_| match archive {
+            true => Attributes::of(src_path)?.apply(&temp_path),
+            false => Ok(()),
+        })
+        .and_then(|_| rename(&temp_path, dest_path).map_err(Error::io(dest_path)));
+    if copied.is_err() {
+        let _ = remove_file(&temp_path);
+    }
+    copied
+}
+
+/// Creates the link next to the destination and renames it over whatever is there.
+///
+/// In archive mode the link gets the owner and timestamps of the source link first.
+fn create_link(target: &Path, src_path: &Path, dest_path: &Path, archive: bool) -> Result<()> {
+    let temp_path = temp_sibling(dest_path);
+
+    let linked = symlink(target, &temp_path)
+        .map_err(Error::io(dest_path))
+        .and_then(|_| match archive {
+            true => copy_link_metadata(src_path, &temp_path),
+            false => Ok(()),
+        })
+        .and_then(|_| rename(&temp_path, dest_path).map_err(Error::io(dest_path)));
+    if linked.is_err() {
+        let _ = remove_file(&temp_path);
+    }
+    linked
+}
+
+/// Makes a hard link to the original next to the destination and renames it into place.
+///
+/// Renaming a link over another link to the same file does nothing, so the sibling is
+/// removed afterwards in any case.
+fn create_hard_link(original: &Path, dest_path: &Path) -> Result<()> {
+    let temp_path = temp_sibling(dest_path);
+
+    let linked = hard_link(original, &temp_path)
+        .and_then(|_| rename(&temp_path, dest_path))
+        .map_err(Error::io(dest_path));
+    let _ = remove_file(&temp_path);
+    linked
+}
+
+/// Makes a FIFO, socket or device node like the source one next to the destination and
+/// renames it into place.
+///
+/// The timestamps are set by path, since opening a FIFO waits for the other end.
+fn create_special(src_path: &Path, dest_path: &Path, archive: bool) -> Result<()> {
+    let src_meta = symlink_metadata(src_path).map_err(Error::io(src_path))?;
+    let temp_path = temp_sibling(dest_path);
+
+    let made = make_special(&temp_path, src_meta.mode(), src_meta.rdev())
+        .and_then(|_| set_path_times(&temp_path, &src_meta))
+        .map_err(Error::io(dest_path))
+        .and_then(|_| match archive {
+            true => Attributes::of(src_path)?.apply(&temp_path),
+            false => Ok(()),
+        })
+        .and_then(|_| rename(&temp_path, dest_path).map_err(Error::io(dest_path)));
+    if made.is_err() {
+        let _ = remove_file(&temp_path);
+    }
+    made
+}
+
+/// Checks whether the path itself is a link, without following it.
+fn is_symlink(path: &Path) -> bool {
+    symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink())
+}
+
+/// Renames a destination file to its new path and gives it the metadata of the source file.
+fn move_file(from_path: &Path, src_path: &Path, dest_path: &Path, archive: bool) -> Result<()> {
+    rename(from_path, dest_path).map_err(Error::io(from_path))?;
+    copy_metadata(src_path, dest_path)?;
+    if archive {
+        Attributes::of(src_path)?.apply(dest_path)?;
+    }
+    Ok(())
+}
+
+/// Sets the access time, modification time and permissions of the source on the destination.
+fn copy_metadata(src_path: &Path, dest_path: &Path) -> Result<()> {
+    let src_meta = metadata(src_path).map_err(Error::io(src_path))?;
+    set_metadata(&src_meta, src_path, dest_path)
+}
+
+fn set_metadata(src_meta: &Metadata, src_path: &Path, dest_path: &Path) -> Result<()> {
+    let times = FileTimes::new()
+        .set_accessed(src_meta.accessed().map_err(Error::io(src_path))?)
+        .set_modified(src_meta.modified().map_err(Error::io(src_path))?);
+
+    File::open(dest_path)
+        .and_then(|file| file.set_times(times))
+        .map_err(Error::io(dest_path))?;
+    set_permissions(dest_path, src_meta.permissions()).map_err(Error::io(dest_path))?;
+    Ok(())
+}
+
+fn write_temp(src_path: &Path, temp_path: &Path) -> Result<()> {
+    // Read before copying, since reading the content updates the access time
+    let src_meta = metadata(src_path).map_err(Error::io(src_path))?;
+    if is_sparse(&src_meta) {
+        copy_sparse(src_path, temp_path)?;
+    } else {
+        copy(src_path, temp_path).map_err(Error::io(src_path))?;
+    }
+    set_metadata(&src_meta, src_path, temp_path)
+}
+
+/// Gives the step of the plan an operation belongs to.
+///
+/// Every step is finished before the next one starts, so the directories exist before
+/// their files are copied and are empty before they are removed.
+fn phase(kind: OperationKind) -> u8 {
+    match kind {
+        OperationKind::Replace => 0,
+        OperationKind::Mkdir => 1,
+        OperationKind::Move
+        | OperationKind::Create
+        | OperationKind::Update
+        | OperationKind::Link
+        | OperationKind::Mknod => 2,
+        OperationKind::HardLink => 3,
+        OperationKind::Delete => 4,
+        OperationKind::Rmdir => 5,
+        OperationKind::Attrs => 6,
+    }
+}
+
 /// Implementation for actions performed on the files.
 impl SyncData {
-    /// It copies the files and directories from the source to the destination.
+    /// Applies the operations of a sync plan to the destination.
+    ///
+    /// Takes:
+    /// - The sync plan to apply
     ///
-    /// Lists the files and directories, finds the relative files and directories and checks the existance of them.
+    /// Returns:
+    /// - Report of the applied operations and the paths that failed
+    /// - Error of the first failed path, unless the sync continues on error
     ///
-    /// Copies them if there is no existance in the destination.
+    /// Goes through the operations in their order, joins the relative path with the
+    /// source and destination, and creates, copies or removes it accordingly.
+    /// The files of each step are copied or removed on up to `jobs` threads, and the
+    /// report keeps the order of the plan. With `inplace`, modified files above the delta
+    /// threshold are updated in place with only their changed blocks, unless `whole_file` is set.
+    /// Copied files keep the access time, modification time and permissions of the source.
     ///
     /// # Example
     ///
@@ -26,44 +219,154 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
-    /// sync.copy_src_to_dest();
+    /// let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    /// let report = sync.apply_plan(&plan).expect("[ERROR]: failed to apply the plan");
+    /// report.print_failures();
     /// ```
-    pub fn copy_src_to_dest(&self) {
-        let src_dirs = self.list_src_dirs();
-        let src_files = self.list_src_files();
+    pub fn apply_plan(&self, plan: &SyncPlan) -> Result<SyncReport> {
+        let mut report = SyncReport::default();
 
-        for entry in src_dirs {
-            if entry == self.source {
-                continue;
-            }
+        for ops in plan
+            .operations
+            .chunk_by(|a, b| phase(a.kind) == phase(b.kind))
+        {
+            // Directories depend on each other, so only the files are spread over the threads
+            let workers = match ops[0].kind {
+                OperationKind::Mkdir | OperationKind::Rmdir => 1,
+                _ => self.worker_count(),
+            };
 
-            let relative_dir = entry.strip_prefix(&self.source);
-            match relative_dir {
-                Ok(dir) => {
-                    let relative_path = self.destination.join(dir);
-                    if !relative_path.exists() {
-                        create_dir_all(relative_path)
-                            .expect("[ERROR]: failed to create the directories");
-                    }
+            for applied in self.run_on(workers, ops, |op| self.apply_operation(plan, op)) {
+                if let Some(saved) = self.recover(applied, &mut report.failures)? {
+                    report.applied += 1;
+                    report.bytes_saved += saved;
                 }
-                Err(e) => eprintln!("[ERROR]: {}", e),
             }
         }
+        Ok(report)
+    }
 
-        for entry in src_files {
-            let relative_file = entry.strip_prefix(&self.source);
-            match relative_file {
-                Ok(file) => {
-                    let relative_path = self.destination.join(file);
-                    if !relative_path.exists() {
-                        copy(entry, relative_path).expect("[ERROR]: failed copy the file");
-                    }
+    /// Applies a single operation and gives the number of bytes it did not have to write.
+    fn apply_operation(&self, plan: &SyncPlan, op: &SyncOperation) -> Result<u64> {
+        let src_path = self.source.join(&op.path);
+        let dest_path = self.dest_entry(&plan.names, &op.path);
+
+        match op.kind {
+            OperationKind::Replace => {
+                let meta = symlink_metadata(&dest_path).map_err(Error::io(&dest_path))?;
+                if meta.is_dir() {
+                    remove_dir_all(&dest_path).map_err(Error::io(&dest_path))?;
+                } else {
+                    remove_file(&dest_path).map_err(Error::io(&dest_path))?;
+                }
+            }
+            OperationKind::Mkdir => {
+                // A link in the way is replaced, so nothing is written through it
+                if is_symlink(&dest_path) {
+                    remove_file(&dest_path).map_err(Error::io(&dest_path))?;
+                }
+                create_dir_all(&dest_path).map_err(Error::io(&dest_path))?;
+            }
+            OperationKind::Move => {
+                let from = op.from.as_ref().unwrap_or(&op.path);
+                move_file(
+                    &self.dest_entry(&plan.names, from),
+                    &src_path,
+                    &dest_path,
+                    self.archive,
+                )?;
+            }
+            OperationKind::Create => {
+                copy_file(&src_path, &dest_path, self.archive)?;
+            }
+            OperationKind::Update if self.uses_delta(op.size) && dest_path.is_file() => {
+                let saved = self.delta_update(&src_path, &dest_path)?;
+                copy_metadata(&src_path, &dest_path)?;
+                if self.archive {
+                    self.copy_attributes(&src_path, &dest_path)?;
                 }
-                Err(e) => eprintln!("[ERROR]: {}", e),
+                return Ok(saved);
+            }
+            OperationKind::Update => {
+                copy_file(&src_path, &dest_path, self.archive)?;
+            }
+            OperationKind::Link => {
+                let target = read_link(&src_path).map_err(Error::io(&src_path))?;
+                create_link(
+                    &self.link_target(&op.path, &target),
+                    &src_path,
+                    &dest_path,
+                    self.archive,
+                )?;
+            }
+            OperationKind::Mknod => {
+                create_special(&src_path, &dest_path, self.archive)?;
+            }
+            OperationKind::HardLink => {
+                let leader = op.from.as_ref().unwrap_or(&op.path);
+                create_hard_link(&self.dest_entry(&plan.names, leader), &dest_path)?;
+            }
+            OperationKind::Delete => {
+                remove_file(&dest_path).map_err(Error::io(&dest_path))?;
+            }
+            OperationKind::Rmdir => {
+                remove_dir(&dest_path).map_err(Error::io(&dest_path))?;
+            }
+            // A preserved link gets the metadata of the source link, not of its target
+            OperationKind::Attrs if self.links == LinkPolicy::Preserve && is_symlink(&src_path) => {
+                copy_link_metadata(&src_path, &dest_path)?;
+            }
+            OperationKind::Attrs => {
+                copy_metadata(&src_path, &dest_path)?;
+                self.copy_attributes(&src_path, &dest_path)?;
             }
         }
+        Ok(0)
+    }
+
+    /// It copies the files and directories from the source to the destination.
+    ///
+    /// Plans the sync and applies only the directory creation and file creation operations,
+    /// so the files that already exist in the destination are left as they are. A destination
+    /// entry of another type that is in the way of a new entry is removed first. Renamed files
+    /// are copied to their new path, since moving them would change the destination files.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use cover_files::sync::sync::SyncData;
+    /// use std::path::PathBuf;
+    ///
+    /// let sync = SyncData {
+    ///     source: PathBuf::from("source_directory"),
+    ///     destination: PathBuf::from("destination_directory"),
+    ///     changed_only: true,
+    ///     delete: false,
+    ///     verbose: false,
+    ///     dry_run: false,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// sync.copy_src_to_dest().expect("[ERROR]: failed to copy the files");
+    /// ```
+    pub fn copy_src_to_dest(&self) -> Result<SyncReport> {
+        let mut plan = self.scan_and_plan(false)?;
+        plan.retain(|op| {
+            matches!(
+                op.kind,
+                OperationKind::Replace
+                    | OperationKind::Mkdir
+                    | OperationKind::Create
+                    | OperationKind::HardLink
+            )
+        });
+
+        let mut report = self.apply_plan(&plan)?;
+        report.failures.splice(0..0, plan.failures);
+        Ok(report)
     }
 
     /// Updates the destination file when the modification happens in the source.
@@ -71,9 +374,10 @@ impl SyncData {
     /// Takes:
     /// - List of modified files
     ///
-    /// Goes through the list of source files, iters by trimming the source from it. Goes through the loop and find the relative path.
+    /// Keeps only the modified files that are present in the source and turns them into
+    /// update operations of a plan.
     ///
-    /// Removes a file if it exists in the destination and copy the modified one from the source.
+    /// Writes the modified file from the source to a temporary sibling and renames it over the destination file.
     ///
     /// # Example
     ///
@@ -88,44 +392,43 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
-    /// let (modified_src_file, src_modified) = sync.src_file_modified();
-    /// println!("File: {:?}\n Modification status: {}", modified_src_file, src_modified);
+    /// let (modified_src_file, src_modified) = sync
+    ///     .src_file_modified()
+    ///     .expect("[ERROR]: failed to check the modification");
+    /// if src_modified {
+    ///     sync.update_dest_file(modified_src_file).expect("[ERROR]: failed to update the files");
+    /// }
     /// ```
-    pub fn update_dest_file(&self, file_names: Vec<PathBuf>) {
-        let src_files = self.list_src_files();
-        let relative_src = src_files
-            .iter()
-            .map(|f| {
-                f.strip_prefix(&self.source)
-                    .expect("[ERROR]: failed to get the file")
-                    .to_path_buf()
+    pub fn update_dest_file(&self, file_names: Vec<PathBuf>) -> Result<SyncReport> {
+        let operations = file_names
+            .into_iter()
+            .filter(|file| self.source.join(file).is_file())
+            .map(|file| SyncOperation {
+                kind: OperationKind::Update,
+                size: metadata(self.source.join(&file)).map_or(0, |meta| meta.len()),
+                path: file,
+                from: None,
             })
-            .collect::<Vec<PathBuf>>();
-
-        for file in file_names {
-            for relative in relative_src.clone() {
-                if file == relative {
-                    let src_file = self.source.join(&file);
-                    let dest_file = self.destination.join(&file);
+            .collect();
 
-                    remove_file(&dest_file).expect("[ERROR]: failed to remove the file");
-                    copy(&src_file, dest_file).expect("[ERROR]: failed to copy the file");
-                }
-            }
-        }
+        self.apply_plan(&SyncPlan {
+            operations,
+            ..Default::default()
+        })
     }
 
-    /// Removes the destination file if it is not found in the source
+    /// Removes the destination files and directories that are not found in the source
     ///
     /// This function compares the source and destination directories by their
     /// relative paths. Any file or directory that exists in the destination
     /// but not in the source is removed.
     ///
-    /// The comparison is performed separately for directories and files.
-    /// If an extra file is found, it is removed first. Otherwise, the
-    /// deepest extra directory is removed.
+    /// All the extra files are removed first, then the extra directories
+    /// are removed from the deepest to the shallowest one. The old path of a renamed
+    /// file is extra as well, since it is not moved.
     ///
     /// # Example
     ///
@@ -140,127 +443,24 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
-    /// sync.remove_dest_file();
+    /// sync.remove_dest_file().expect("[ERROR]: failed to remove the files");
     /// ```
-    pub fn remove_dest_file(&self) {
-        let src_dirs = self.list_src_dirs();
-        let dest_dirs = self.list_dest_dirs();
-        let src_files = self.list_src_files();
-        let dest_files = self.list_dest_files();
-        let mut src_data: Vec<PathBuf> = Vec::new();
-        let mut dest_data: Vec<PathBuf> = Vec::new();
-        let mut source_data: Vec<PathBuf> = Vec::new();
-        let mut destination_data: Vec<PathBuf> = Vec::new();
-
-        // Directories
-        for entry in src_dirs {
-            if entry == self.source {
-                continue;
-            }
-
-            let relative_dir = entry
-                .strip_prefix(&self.source)
-                .expect("[ERROR]: failed to get the file")
-                .to_path_buf();
-
-            src_data.push(relative_dir);
-        }
-
-        for entry in dest_dirs {
-            if entry == self.destination {
-                continue;
-            }
-
-            let relative_dir = entry
-                .strip_prefix(&self.destination)
-                .expect("[ERROR]: failed to get the file")
-                .to_path_buf();
+    pub fn remove_dest_file(&self) -> Result<SyncReport> {
+        let mut plan = self.scan_and_plan(false)?;
+        plan.retain(|op| matches!(op.kind, OperationKind::Delete | OperationKind::Rmdir));
 
-            dest_data.push(relative_dir);
-        }
-
-        let src: HashSet<PathBuf> = src_data.into_iter().collect();
-        let dest: HashSet<PathBuf> = dest_data.into_iter().collect();
-        let mut combine_dir = Vec::new();
-
-        for d in dest.difference(&src) {
-            let dest_path = self.destination.join(d);
-            let dest_len = dest_path.as_os_str().len();
-            combine_dir.push((dest_path, dest_len));
-        }
-
-        let max_dir_val = combine_dir.iter().fold(None, |acc, item| match acc {
-            None => Some(item),
-            Some(current_max) => {
-                if item.1 > current_max.1 {
-                    Some(item)
-                } else {
-                    Some(current_max)
-                }
-            }
-        });
-
-        // Files
-        for entry in src_files {
-            let relative_dir = entry
-                .strip_prefix(&self.source)
-                .expect("[ERROR]: failed to get the file")
-                .to_path_buf();
-
-            source_data.push(relative_dir);
-        }
-
-        for entry in dest_files {
-            let relative_dir = entry
-                .strip_prefix(&self.destination)
-                .expect("[ERROR]: failed to file")
-                .to_path_buf();
-
-            destination_data.push(relative_dir);
-        }
-
-        let src: HashSet<PathBuf> = source_data.into_iter().collect();
-        let dest: HashSet<PathBuf> = destination_data.into_iter().collect();
-        let mut combine_file = Vec::new();
-
-        for d in dest.difference(&src) {
-            let dest_path = self.destination.join(d);
-            let dest_len = dest_path.as_os_str().len();
-            combine_file.push((dest_path, dest_len));
-        }
-
-        let max_file_val = combine_file.iter().fold(None, |acc, item| match acc {
-            None => Some(item),
-            Some(current_max) => {
-                if item.1 > current_max.1 {
-                    Some(item)
-                } else {
-                    Some(current_max)
-                }
-            }
-        });
-
-        match max_file_val {
-            Some(file) => {
-                let dest_file = file.0.clone();
-                remove_file(dest_file).expect("[ERROR]: failed to remove the file");
-            }
-            None => {
-                let dest_dir = max_dir_val
-                    .expect("[ERROR]: failed to get the directory")
-                    .0
-                    .clone();
-                remove_dir(dest_dir).expect("[ERROR]: failed to remove the directory");
-            }
-        }
+        let mut report = self.apply_plan(&plan)?;
+        report.failures.splice(0..0, plan.failures);
+        Ok(report)
     }
 
     /// Removes all the destination files even if they exist in the source
     ///
-    /// Lists all the destination directories and files, iter them.
-    /// Checks the existance and remove them all.
+    /// Scans the destination once, removes every file and then the directories
+    /// from the deepest one up. Excluded paths and the directories holding them are kept.
     ///
     /// # Example
     ///
@@ -275,32 +475,49 @@ impl SyncData {
     ///     delete: false,
     ///     verbose: false,
     ///     dry_run: false,
+    ///     ..Default::default()
     /// };
     ///
-    /// sync.remove_all_dest_files();
+    /// sync.remove_all_dest_files().expect("[ERROR]: failed to remove the files");
     /// ```
-    pub fn remove_all_dest_files(&self) {
-        let dest_dirs = self.list_dest_dirs();
-        let dest_files = self.list_dest_files();
+    pub fn remove_all_dest_files(&self) -> Result<SyncReport> {
+        let mut failures = Vec::new();
+        let dest = self.scan_tree(&self.destination, &mut failures)?;
+
+        let mut report = self.remove_indexed_dest_files(&dest)?;
+        report.failures.splice(0..0, failures);
+        Ok(report)
+    }
+
+    /// Removes every file and directory of the destination index.
+    pub(crate) fn remove_indexed_dest_files(&self, dest: &TreeIndex) -> Result<SyncReport> {
+        let mut report = SyncReport::default();
+        let dest_dirs: Vec<&PathBuf> = dest
+            .dirs()
+            .filter(|dir| !dest.holds_excluded(dir))
+            .collect();
         let mut removed = false;
 
-        for entry in dest_files {
-            if entry == self.destination {
-                continue;
-            }
-            if entry.exists() {
+        for file in dest.files().chain(dest.links()).chain(dest.specials()) {
+            let entry = dest.absolute(file);
+            if entry.exists() || is_symlink(&entry) {
                 removed = true;
-                remove_file(entry).expect("[ERROR]: failed to remove the file");
+                let result = remove_file(&entry).map_err(Error::io(&entry));
+                if self.recover(result, &mut report.failures)?.is_some() {
+                    report.applied += 1;
+                }
             }
         }
 
-        for entry in dest_dirs.iter().rev() {
-            if *entry == self.destination {
-                continue;
-            }
+        // Children are indexed after their parents, so the reverse order removes them first
+        for dir in dest_dirs.into_iter().rev() {
+            let entry = dest.absolute(dir);
             if entry.exists() {
                 removed = true;
-                remove_dir(entry).expect("[ERROR]: failed to remove the directory");
+                let result = remove_dir(&entry).map_err(Error::io(&entry));
+                if self.recover(result, &mut report.failures)?.is_some() {
+                    report.applied += 1;
+                }
             }
         }
 
@@ -309,5 +526,6 @@ impl SyncData {
         } else {
             eprintln!("[MESSAGE]: no files are present to be removed");
         }
+        Ok(report)
     }
 }
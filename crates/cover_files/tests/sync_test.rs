@@ -1,14 +1,44 @@
-use cover_files::sync::{
-    log::{filter_dest_dir, filter_dest_file, filter_src_dir, filter_src_file},
-    sync::SyncData,
+// The original tests keep the style they were written in
+#![allow(
+    clippy::bool_assert_comparison,
+    clippy::collapsible_if,
+    clippy::double_ended_iterator_last,
+    clippy::len_zero,
+    clippy::needless_borrows_for_generic_args,
+    clippy::single_match,
+    clippy::unnecessary_to_owned
+)]
+
+use cover_files::{
+    Error,
+    sync::{
+        archive::is_root,
+        collision::{NameFolding, collision_reason, name_collisions},
+        escape::{escaped, unescape},
+        exclude::FilterRule,
+        links::LinkPolicy,
+        log::{filter_dest_dir, filter_dest_file, filter_src_dir, filter_src_file},
+        mirror::DeleteTiming,
+        named::Named,
+        plan::{OperationKind, SyncOperation},
+        select::FileType,
+        special::SpecialPolicy,
+        sync::SyncData,
+        target::{TargetFs, decode_name, encode_name},
+    },
 };
 use std::{
     env,
     fs::{
-        File, create_dir_all, metadata, read_dir, read_to_string, remove_dir_all, remove_file,
-        write,
+        File, FileTimes, Permissions, create_dir_all, hard_link, metadata, read_dir, read_link,
+        read_to_string, remove_dir_all, remove_file, set_permissions, write,
+    },
+    io::{Seek, SeekFrom, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt, PermissionsExt, chown, lchown, symlink},
     },
-    path::PathBuf,
+    path::{Path, PathBuf},
     thread,
     time::{self, UNIX_EPOCH},
 };
@@ -39,6 +69,7 @@ fn test_src_dest_dir_present() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     assert!(
@@ -75,6 +106,7 @@ fn test_single_command_selected() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     assert!(
@@ -109,10 +141,13 @@ fn test_list_src_files() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     let mut searched_file: Vec<PathBuf> = Vec::new();
-    let searched = sync.list_src_files();
+    let searched = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
     for file in searched {
         let filename = file
             .file_name()
@@ -149,10 +184,13 @@ fn test_list_src_dirs() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     let mut searched_dir: Vec<PathBuf> = Vec::new();
-    let searched = sync.list_src_dirs();
+    let searched = sync
+        .list_src_dirs()
+        .expect("[ERROR]: failed to list the source directories");
     for dir in &searched {
         let data = dir
             .iter()
@@ -193,10 +231,13 @@ fn test_list_dest_files() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     let mut searched_file: Vec<PathBuf> = Vec::new();
-    let searched = sync.list_dest_files();
+    let searched = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
     for file in searched {
         let filename = file
             .file_name()
@@ -233,10 +274,13 @@ fn test_list_dest_dirs() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     let mut searched_dir: Vec<PathBuf> = Vec::new();
-    let searched = sync.list_dest_dirs();
+    let searched = sync
+        .list_dest_dirs()
+        .expect("[ERROR]: failed to list the destination directories");
     for dir in &searched {
         let data = dir
             .iter()
@@ -288,19 +332,28 @@ fn test_file_timestamp() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files_list = sync.list_src_files();
-    let dest_files_list = sync.list_dest_files();
+    let src_files_list = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files_list = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
-    let src_timestamp = sync.file_timestamp(src_files_list.clone(), &sync.source);
-    let dest_timestamp = sync.file_timestamp(dest_files_list.clone(), &sync.destination);
+    let src_timestamp = sync
+        .file_timestamp(src_files_list.clone(), &sync.source)
+        .expect("[ERROR]: failed to get the timestamp");
+    let dest_timestamp = sync
+        .file_timestamp(dest_files_list.clone(), &sync.destination)
+        .expect("[ERROR]: failed to get the timestamp");
 
     for (path, dest_time) in &dest_timestamp {
         let src = src_timestamp.get(path);
         match src {
             Some(src_time) => {
-                assert!(*src_time != 0.0 && *dest_time != 0.0);
+                assert!(!src_time.is_zero() && !dest_time.is_zero());
             }
             None => {}
         }
@@ -354,9 +407,12 @@ fn test_files_are_equal() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let equal = sync.files_are_equal(&src_sub_file, &dest_sub_file);
+    let equal = sync
+        .files_are_equal(&src_sub_file, &dest_sub_file)
+        .expect("[ERROR]: failed to compare the files");
     assert!(equal);
 
     remove_file(&src_sub_file).ok();
@@ -401,9 +457,12 @@ fn test_src_file_created() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let created = sync.src_file_created();
+    let created = sync
+        .src_file_created()
+        .expect("[ERROR]: failed to check the creation");
     assert_eq!(created, file_found);
 
     remove_file(&src_sub_file).ok();
@@ -479,9 +538,12 @@ fn test_src_file_modified() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let (file_modified, is_modified) = sync.src_file_modified();
+    let (file_modified, is_modified) = sync
+        .src_file_modified()
+        .expect("[ERROR]: failed to check the modification");
     for file in file_modified {
         assert_eq!(file.to_string_lossy(), modified_file.to_string_lossy());
     }
@@ -528,9 +590,12 @@ fn test_dest_file_created() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let created = sync.dest_file_created();
+    let created = sync
+        .dest_file_created()
+        .expect("[ERROR]: failed to check the creation");
     assert_eq!(created, file_found);
 
     remove_file(&dest_sub_file).ok();
@@ -607,9 +672,12 @@ fn test_dest_file_modified() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let (file_modified, is_modified) = sync.dest_file_modified();
+    let (file_modified, is_modified) = sync
+        .dest_file_modified()
+        .expect("[ERROR]: failed to check the modification");
     for file in file_modified {
         assert_eq!(file.to_string_lossy(), modified_file.to_string_lossy());
     }
@@ -655,12 +723,18 @@ fn test_copy_src_to_destination() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    sync.copy_src_to_dest();
+    sync.copy_src_to_dest()
+        .expect("[ERROR]: failed to copy the files");
 
-    let src_files = sync.list_src_files();
-    let dest_files = sync.list_dest_files();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
     let src: Vec<PathBuf> = src_files
         .iter()
@@ -719,12 +793,18 @@ fn test_remove_dest_files() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    sync.remove_dest_file();
+    sync.remove_dest_file()
+        .expect("[ERROR]: failed to remove the files");
 
-    let src_files = sync.list_src_files();
-    let dest_files = sync.list_dest_files();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
     let src: Vec<PathBuf> = src_files
         .iter()
@@ -791,15 +871,23 @@ fn test_update_dest_file() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let (modified_src_file, src_modified) = sync.src_file_modified();
+    let (modified_src_file, src_modified) = sync
+        .src_file_modified()
+        .expect("[ERROR]: failed to check the modification");
     if src_modified {
-        sync.update_dest_file(modified_src_file.clone());
+        sync.update_dest_file(modified_src_file.clone())
+            .expect("[ERROR]: failed to update the files");
     }
 
-    let src_files = sync.list_src_files();
-    let dest_files = sync.list_dest_files();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
     for src_file in src_files {
         for dest_file in &dest_files {
@@ -843,10 +931,14 @@ fn test_remove_all_dest_files() {
         delete: true,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
-    sync.remove_all_dest_files();
+    sync.remove_all_dest_files()
+        .expect("[ERROR]: failed to remove the files");
 
-    let dest_files = sync.list_dest_files();
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
     let dest: Vec<PathBuf> = dest_files
         .iter()
         .filter_map(|f| {
@@ -862,75 +954,6 @@ fn test_remove_all_dest_files() {
     remove_dir_all(&dest_parent_dir).ok();
 }
 
-#[test]
-fn src_files_present_or_not() {
-    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
-    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_file_present");
-
-    if src_parent_dir.exists() {
-        remove_dir_all(&src_parent_dir).ok();
-    }
-
-    let src_sub_dir = src_parent_dir.join("test_dir");
-    let src_sub_file = src_sub_dir.join("common.txt");
-
-    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
-    create_dir_all(&src_sub_dir).expect("[ERROR]: failed to create a source directory");
-    File::create(&src_sub_file).expect("[ERROR]: failed to create a source file");
-
-    let sync = SyncData {
-        source: src_parent_dir.clone(),
-        destination: PathBuf::new(),
-        changed_only: true,
-        delete: false,
-        verbose: false,
-        dry_run: false,
-    };
-
-    let (dir_list, file_list) = sync.get_file_names();
-    assert!(dir_list.len() != 0 && file_list.len() != 0);
-
-    remove_file(&src_sub_file).ok();
-    remove_dir_all(&src_sub_dir).ok();
-    remove_dir_all(&src_parent_dir).ok();
-}
-
-#[test]
-fn test_has_duplicates() {
-    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
-    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_has_duplicates");
-
-    if src_parent_dir.exists() {
-        remove_dir_all(&src_parent_dir).ok();
-    }
-
-    let src_sub_dir = src_parent_dir.join("test_dir");
-    let src_sub_file1 = src_parent_dir.join("common.txt");
-    let src_sub_file2 = src_sub_dir.join("common.txt");
-
-    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
-    create_dir_all(&src_sub_dir).expect("[ERROR]: failed to create a source directory");
-    File::create(&src_sub_file1).expect("[ERROR]: failed to create a source file");
-    File::create(&src_sub_file2).expect("[ERROR]: failed to create a source file");
-
-    let sync = SyncData {
-        source: src_parent_dir.clone(),
-        destination: PathBuf::new(),
-        changed_only: true,
-        delete: false,
-        verbose: false,
-        dry_run: false,
-    };
-
-    let result = sync.has_duplicates();
-    assert!(result == true);
-
-    remove_file(&src_sub_file1).ok();
-    remove_file(&src_sub_file2).ok();
-    remove_dir_all(&src_sub_dir).ok();
-    remove_dir_all(&src_parent_dir).ok();
-}
-
 #[test]
 fn test_filter_src_dirs() {
     let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
@@ -952,9 +975,12 @@ fn test_filter_src_dirs() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let list_dirs = sync.list_src_dirs();
+    let list_dirs = sync
+        .list_src_dirs()
+        .expect("[ERROR]: failed to list the source directories");
     let filtered = filter_src_dir(&list_dirs, &sync.source);
     let dir = src_sub_dir
         .iter()
@@ -991,9 +1017,12 @@ fn test_filter_dest_dirs() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let list_dirs = sync.list_dest_dirs();
+    let list_dirs = sync
+        .list_dest_dirs()
+        .expect("[ERROR]: failed to list the destination directories");
     let filtered = filter_dest_dir(&list_dirs, &sync.destination);
     let dir = dest_sub_dir
         .iter()
@@ -1032,9 +1061,12 @@ fn test_filter_src_files() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let list_files = sync.list_src_files();
+    let list_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
     let filtered = filter_src_file(&list_files);
     let file = src_sub_file
         .file_name()
@@ -1071,9 +1103,12 @@ fn test_filter_dest_files() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let list_files = sync.list_dest_files();
+    let list_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
     let filtered = filter_dest_file(&list_files);
     let file = dest_sub_file
         .file_name()
@@ -1107,6 +1142,7 @@ fn test_src_dir_present() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     assert!(
@@ -1134,6 +1170,7 @@ fn test_dest_dir_present() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     assert!(
@@ -1153,6 +1190,7 @@ fn test_dir_not_present() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     assert!(!sync.src_dest_dir_present(), "No directory is detected");
@@ -1183,6 +1221,7 @@ fn test_multiple_commands_selected() {
         delete: true,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     assert!(
@@ -1219,6 +1258,7 @@ fn test_no_command_selected() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     assert!(
@@ -1251,13 +1291,18 @@ fn test_source_empty() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     let entries = read_dir(&src_parent_dir).expect("[ERROR]: failed to read the source directory");
     let searched_files: Vec<PathBuf> = entries.filter_map(|f| f.ok()).map(|f| f.path()).collect();
 
-    let src_files = sync.list_src_files();
-    let src_dirs = sync.list_src_dirs();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let src_dirs = sync
+        .list_src_dirs()
+        .expect("[ERROR]: failed to list the source directories");
 
     for file in src_files {
         list_file.push(file);
@@ -1299,13 +1344,18 @@ fn test_dest_empty() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
     let entries = read_dir(&dest_parent_dir).expect("[ERROR]: failed to read the directory");
     let searched_files: Vec<PathBuf> = entries.filter_map(|f| f.ok()).map(|f| f.path()).collect();
 
-    let dest_files = sync.list_dest_files();
-    let dest_dirs = sync.list_dest_dirs();
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
+    let dest_dirs = sync
+        .list_dest_dirs()
+        .expect("[ERROR]: failed to list the destination directories");
 
     for file in dest_files {
         list_file.push(file);
@@ -1358,17 +1408,26 @@ fn test_src_timestamp_exists_dest_not() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files_list = sync.list_src_files();
-    let dest_files_list = sync.list_dest_files();
+    let src_files_list = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files_list = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
-    let src_timestamp = sync.file_timestamp(src_files_list.clone(), &sync.source);
-    let dest_timestamp = sync.file_timestamp(dest_files_list.clone(), &sync.destination);
+    let src_timestamp = sync
+        .file_timestamp(src_files_list.clone(), &sync.source)
+        .expect("[ERROR]: failed to get the timestamp");
+    let dest_timestamp = sync
+        .file_timestamp(dest_files_list.clone(), &sync.destination)
+        .expect("[ERROR]: failed to get the timestamp");
 
     for (path, src_time) in &src_timestamp {
         if !dest_timestamp.contains_key(path) {
-            assert!(*src_time != 0.0)
+            assert!(!src_time.is_zero())
         }
     }
 
@@ -1427,13 +1486,22 @@ fn test_src_timestamp_greater_than_dest() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files_list = sync.list_src_files();
-    let dest_files_list = sync.list_dest_files();
+    let src_files_list = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files_list = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
-    let src_timestamp = sync.file_timestamp(src_files_list.clone(), &sync.source);
-    let dest_timestamp = sync.file_timestamp(dest_files_list.clone(), &sync.destination);
+    let src_timestamp = sync
+        .file_timestamp(src_files_list.clone(), &sync.source)
+        .expect("[ERROR]: failed to get the timestamp");
+    let dest_timestamp = sync
+        .file_timestamp(dest_files_list.clone(), &sync.destination)
+        .expect("[ERROR]: failed to get the timestamp");
 
     for (path, src_time) in &src_timestamp {
         let dest = dest_timestamp.get(path);
@@ -1491,13 +1559,22 @@ fn test_dest_timestamp_greater_than_src() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files_list = sync.list_src_files();
-    let dest_files_list = sync.list_dest_files();
+    let src_files_list = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files_list = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
-    let src_timestamp = sync.file_timestamp(src_files_list.clone(), &sync.source);
-    let dest_timestamp = sync.file_timestamp(dest_files_list.clone(), &sync.destination);
+    let src_timestamp = sync
+        .file_timestamp(src_files_list.clone(), &sync.source)
+        .expect("[ERROR]: failed to get the timestamp");
+    let dest_timestamp = sync
+        .file_timestamp(dest_files_list.clone(), &sync.destination)
+        .expect("[ERROR]: failed to get the timestamp");
 
     for (path, dest_time) in &dest_timestamp {
         let src = src_timestamp.get(path);
@@ -1554,13 +1631,22 @@ fn test_src_timestamp_equal_to_dest() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files_list = sync.list_src_files();
-    let dest_files_list = sync.list_dest_files();
+    let src_files_list = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files_list = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
-    let src_timestamp = sync.file_timestamp(src_files_list.clone(), &sync.source);
-    let dest_timestamp = sync.file_timestamp(dest_files_list.clone(), &sync.destination);
+    let src_timestamp = sync
+        .file_timestamp(src_files_list.clone(), &sync.source)
+        .expect("[ERROR]: failed to get the timestamp");
+    let dest_timestamp = sync
+        .file_timestamp(dest_files_list.clone(), &sync.destination)
+        .expect("[ERROR]: failed to get the timestamp");
 
     for (path, src_time) in &src_timestamp {
         let dest = dest_timestamp.get(path);
@@ -1597,9 +1683,12 @@ fn test_src_file_not_created() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let created = sync.src_file_created();
+    let created = sync
+        .src_file_created()
+        .expect("[ERROR]: failed to check the creation");
     assert_eq!(
         created, false,
         "Source file incorrectly detected as created"
@@ -1674,9 +1763,12 @@ fn test_src_file_not_modified() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let (file_modified, is_modified) = sync.src_file_modified();
+    let (file_modified, is_modified) = sync
+        .src_file_modified()
+        .expect("[ERROR]: failed to check the modification");
     for file in &file_modified {
         assert_eq!(file.to_string_lossy(), modified_file.to_string_lossy());
     }
@@ -1707,9 +1799,12 @@ fn test_dest_file_not_created() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let created = sync.dest_file_created();
+    let created = sync
+        .dest_file_created()
+        .expect("[ERROR]: failed to check the creation");
     assert_eq!(
         created, false,
         "Destination file incorrectly detected as created"
@@ -1742,12 +1837,18 @@ fn test_src_dest_equal() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    sync.copy_src_to_dest();
+    sync.copy_src_to_dest()
+        .expect("[ERROR]: failed to copy the files");
 
-    let src_files = sync.list_src_files();
-    let dest_files = sync.list_dest_files();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
     let srcfile: Vec<PathBuf> = src_files
         .iter()
@@ -1768,8 +1869,12 @@ fn test_src_dest_equal() {
     assert_eq!(srcfile, destfile);
     assert!(srcfile.len() == 0 && destfile.len() == 0);
 
-    let src_dirs = sync.list_src_dirs();
-    let dest_dirs = sync.list_dest_dirs();
+    let src_dirs = sync
+        .list_src_dirs()
+        .expect("[ERROR]: failed to list the source directories");
+    let dest_dirs = sync
+        .list_dest_dirs()
+        .expect("[ERROR]: failed to list the destination directories");
 
     let mut srcdirs: Vec<PathBuf> = Vec::new();
     let mut destdirs: Vec<PathBuf> = Vec::new();
@@ -1833,10 +1938,15 @@ fn test_dest_files_not_removed() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files = sync.list_src_files();
-    let dest_files = sync.list_dest_files();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
     let srcfile: Vec<PathBuf> = src_files
         .iter()
@@ -1860,8 +1970,12 @@ fn test_dest_files_not_removed() {
     let mut srcdirs: Vec<PathBuf> = Vec::new();
     let mut destdirs: Vec<PathBuf> = Vec::new();
 
-    let src_dirs = sync.list_src_dirs();
-    let dest_dirs = sync.list_dest_dirs();
+    let src_dirs = sync
+        .list_src_dirs()
+        .expect("[ERROR]: failed to list the source directories");
+    let dest_dirs = sync
+        .list_dest_dirs()
+        .expect("[ERROR]: failed to list the destination directories");
 
     for src in src_dirs {
         if src == sync.source {
@@ -1939,10 +2053,15 @@ fn test_dest_file_not_updated() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files = sync.list_src_files();
-    let dest_files = sync.list_dest_files();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
     let mut src_content = String::new();
     let mut dest_content = String::new();
@@ -2000,10 +2119,15 @@ fn test_no_dest_file() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files = sync.list_src_files();
-    let dest_files = sync.list_dest_files();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
     let mut src_content = String::new();
     for src_file in src_files {
@@ -2051,10 +2175,15 @@ fn test_no_src_file() {
         delete: false,
         verbose: false,
         dry_run: false,
+        ..Default::default()
     };
 
-    let src_files = sync.list_src_files();
-    let dest_files = sync.list_dest_files();
+    let src_files = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the source files");
+    let dest_files = sync
+        .list_dest_files()
+        .expect("[ERROR]: failed to list the destination files");
 
     let mut dest_content = String::new();
     for dest_file in dest_files {
@@ -2068,3 +2197,2428 @@ fn test_no_src_file() {
     remove_dir_all(&src_parent_dir).ok();
     remove_dir_all(&dest_parent_dir).ok();
 }
+
+#[test]
+fn test_sync_output_reconciles_all_states() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_all_states");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_all_states");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    let src_new_file = src_parent_dir.join("new.txt");
+    let src_changed_file = src_parent_dir.join("changed.txt");
+    let dest_changed_file = dest_parent_dir.join("changed.txt");
+    let dest_extra_dir = dest_parent_dir.join("extra_dir");
+    let dest_extra_file = dest_extra_dir.join("extra.txt");
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_extra_dir).expect("[ERROR]: failed to create a destination directory");
+    write(&dest_changed_file, "old content").expect("[ERROR]: failed to write the file");
+    write(&dest_extra_file, "extra content").expect("[ERROR]: failed to write the file");
+    thread::sleep(time::Duration::from_secs(1));
+    write(&src_new_file, "new content").expect("[ERROR]: failed to write the file");
+    write(&src_changed_file, "changed content").expect("[ERROR]: failed to write the file");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        delete: false,
+        verbose: false,
+        dry_run: false,
+        ..Default::default()
+    };
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    let new_content = read_to_string(dest_parent_dir.join("new.txt"))
+        .expect("[ERROR]: failed to read the destination file");
+    let changed_content =
+        read_to_string(&dest_changed_file).expect("[ERROR]: failed to read the destination file");
+
+    assert_eq!(new_content, "new content");
+    assert_eq!(changed_content, "changed content");
+    assert!(!dest_extra_file.exists() && !dest_extra_dir.exists());
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_plan_and_apply() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_plan");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_plan");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    let src_sub_dir = src_parent_dir.join("test_dir");
+    let src_sub_file = src_sub_dir.join("new.txt");
+    let dest_extra_file = dest_parent_dir.join("extra.txt");
+
+    create_dir_all(&src_sub_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(&src_sub_file, "new content").expect("[ERROR]: failed to write the file");
+    write(&dest_extra_file, "extra").expect("[ERROR]: failed to write the file");
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        delete: false,
+        verbose: false,
+        dry_run: false,
+        ..Default::default()
+    };
+
+    let mut plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert_eq!(
+        plan.operations,
+        vec![
+            SyncOperation {
+                kind: OperationKind::Mkdir,
+                path: PathBuf::from("test_dir"),
+                size: 0,
+                from: None,
+            },
+            SyncOperation {
+                kind: OperationKind::Create,
+                path: PathBuf::from("test_dir/new.txt"),
+                size: 11,
+                from: None,
+            },
+            SyncOperation {
+                kind: OperationKind::Delete,
+                path: PathBuf::from("extra.txt"),
+                size: 5,
+                from: None,
+            },
+        ]
+    );
+    assert!(!dest_parent_dir.join("test_dir").exists());
+
+    plan.retain(|op| op.kind != OperationKind::Delete);
+    sync.apply_plan(&plan)
+        .expect("[ERROR]: failed to apply the plan");
+
+    assert!(dest_parent_dir.join("test_dir/new.txt").exists());
+    assert!(dest_extra_file.exists());
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .operations
+            .iter()
+            .all(|op| op.kind == OperationKind::Delete)
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_files_are_equal_missing_file() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_missing_file");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_missing_file");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    let src_sub_file = src_parent_dir.join("common.txt");
+    let dest_sub_file = dest_parent_dir.join("common.txt");
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(&src_sub_file, "source content").expect("[ERROR]: failed to write the file");
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+
+    let result = sync.files_are_equal(&src_sub_file, &dest_sub_file);
+    assert!(matches!(result, Err(Error::Io { path, .. }) if path == dest_sub_file));
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_continue_on_error() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_continue");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_continue");
+
+    for continue_on_error in [false, true] {
+        if src_parent_dir.exists() {
+            remove_dir_all(&src_parent_dir).ok();
+        }
+
+        if dest_parent_dir.exists() {
+            remove_dir_all(&dest_parent_dir).ok();
+        }
+
+        // The destination has a directory where the source has a file, and it holds an
+        // excluded file so it is not replaced, so copying the file fails
+        let blocked_dir = dest_parent_dir.join("a.txt");
+        create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+        create_dir_all(&blocked_dir).expect("[ERROR]: failed to create a destination directory");
+        write(blocked_dir.join("keep.lock"), "").expect("[ERROR]: failed to write the file");
+        write(src_parent_dir.join("a.txt"), "a").expect("[ERROR]: failed to write the file");
+        write(src_parent_dir.join("b.txt"), "b").expect("[ERROR]: failed to write the file");
+
+        let mut sync = SyncData {
+            source: src_parent_dir.clone(),
+            destination: dest_parent_dir.clone(),
+            changed_only: true,
+            continue_on_error,
+            filters: vec![FilterRule::Exclude("*.lock".to_string())],
+            ..Default::default()
+        };
+
+        let result = sync.sync_output();
+        if continue_on_error {
+            assert!(matches!(result, Err(Error::Incomplete(1))));
+            assert!(dest_parent_dir.join("b.txt").exists());
+        } else {
+            assert!(matches!(result, Err(Error::Io { .. })));
+            assert!(!dest_parent_dir.join("b.txt").exists());
+        }
+    }
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_copy_preserves_metadata() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_preserve");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_preserve");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    let src_sub_file = src_parent_dir.join("common.txt");
+    let dest_sub_file = dest_parent_dir.join("common.txt");
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(&src_sub_file, "source content").expect("[ERROR]: failed to write the file");
+
+    let past = UNIX_EPOCH + time::Duration::new(1_600_000_000, 123_456_789);
+    File::options()
+        .write(true)
+        .open(&src_sub_file)
+        .and_then(|f| f.set_times(FileTimes::new().set_accessed(past).set_modified(past)))
+        .expect("[ERROR]: failed to set the file times");
+    std::fs::set_permissions(&src_sub_file, Permissions::from_mode(0o640))
+        .expect("[ERROR]: failed to set the permissions");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    let dest_meta = metadata(&dest_sub_file).expect("[ERROR]: failed to get the metadata");
+    assert_eq!(dest_meta.modified().ok(), Some(past));
+    assert_eq!(dest_meta.accessed().ok(), Some(past));
+    assert_eq!(dest_meta.permissions().mode() & 0o777, 0o640);
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .is_empty()
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_sub_second_timestamp_and_modify_window() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_window");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_window");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    let src_sub_file = src_parent_dir.join("common.txt");
+    let dest_sub_file = dest_parent_dir.join("common.txt");
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(&src_sub_file, "source content").expect("[ERROR]: failed to write the file");
+    // Same size as the source, since a different size is a change whatever the window
+    write(&dest_sub_file, "target content").expect("[ERROR]: failed to write the file");
+
+    // Both files are modified within the same second
+    let dest_time = UNIX_EPOCH + time::Duration::new(1_600_000_000, 100_000_000);
+    let src_time = UNIX_EPOCH + time::Duration::new(1_600_000_000, 900_000_000);
+    for (file, time) in [(&src_sub_file, src_time), (&dest_sub_file, dest_time)] {
+        File::options()
+            .write(true)
+            .open(file)
+            .and_then(|f| f.set_times(FileTimes::new().set_modified(time)))
+            .expect("[ERROR]: failed to set the file times");
+    }
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+
+    let (modified, is_modified) = sync
+        .src_file_modified()
+        .expect("[ERROR]: failed to check the modification");
+    assert!(is_modified);
+    assert_eq!(modified, vec![PathBuf::from("common.txt")]);
+
+    let sync = SyncData {
+        modify_window: time::Duration::from_secs(1),
+        ..sync
+    };
+
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .is_empty()
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_checksum_mode() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_checksum");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_checksum");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    let src_sub_file = src_parent_dir.join("common.txt");
+    let dest_sub_file = dest_parent_dir.join("common.txt");
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(&src_sub_file, "restored").expect("[ERROR]: failed to write the file");
+    write(&dest_sub_file, "original").expect("[ERROR]: failed to write the file");
+
+    // The source is restored from a backup with the same timestamp as the destination
+    let same_time = UNIX_EPOCH + time::Duration::from_secs(1_600_000_000);
+    for file in [&src_sub_file, &dest_sub_file] {
+        File::options()
+            .write(true)
+            .open(file)
+            .and_then(|f| f.set_times(FileTimes::new().set_modified(same_time)))
+            .expect("[ERROR]: failed to set the file times");
+    }
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .is_empty()
+    );
+
+    let mut sync = SyncData {
+        checksum: true,
+        ..sync
+    };
+
+    assert!(
+        !sync
+            .checksums_match(&src_sub_file, &dest_sub_file)
+            .expect("[ERROR]: failed to compare the checksums")
+    );
+    let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert!(plan.contains(OperationKind::Update));
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    let dest_content =
+        read_to_string(&dest_sub_file).expect("[ERROR]: failed to read the destination file");
+    assert_eq!(dest_content, "restored");
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_scan_index_reused_by_plan() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_scan_index");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_scan_index");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(src_parent_dir.join("nested"))
+        .expect("[ERROR]: failed to create a source directory");
+    create_dir_all(dest_parent_dir.join("extra"))
+        .expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join("nested/new.txt"), "12345")
+        .expect("[ERROR]: failed to write the file");
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+
+    let index = sync
+        .scan()
+        .expect("[ERROR]: failed to scan the directories");
+    let new_file = PathBuf::from("nested/new.txt");
+    assert!(index.source.is_dir(&PathBuf::from("nested")));
+    assert!(index.source.is_file(&new_file));
+    assert_eq!(index.source.size_of(&new_file), 5);
+    assert!(index.destination.is_dir(&PathBuf::from("extra")));
+    assert!(!index.source.contains(&PathBuf::new()));
+
+    let from_index = sync
+        .plan_from_index(&index)
+        .expect("[ERROR]: failed to plan the sync");
+    let planned = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert_eq!(from_index.operations, planned.operations);
+    assert!(
+        planned
+            .iter()
+            .any(|op| op.kind == OperationKind::Create && op.path == new_file && op.size == 5)
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_parallel_jobs() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_parallel_jobs");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_parallel_jobs");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    for dir in 0..4 {
+        let sub_dir = src_parent_dir.join(format!("dir_{}", dir));
+        create_dir_all(&sub_dir).expect("[ERROR]: failed to create a source directory");
+        for file in 0..8 {
+            write(
+                sub_dir.join(format!("file_{}.txt", file)),
+                format!("{}-{}", dir, file),
+            )
+            .expect("[ERROR]: failed to write the file");
+        }
+    }
+    // Blocks the copy of one file, so the report has a failure in the middle of the plan.
+    // The blocking directory holds an excluded file, so it is not replaced.
+    create_dir_all(dest_parent_dir.join("dir_2"))
+        .expect("[ERROR]: failed to create a destination directory");
+    create_dir_all(dest_parent_dir.join("dir_2/file_3.txt"))
+        .expect("[ERROR]: failed to create a blocking directory");
+    write(dest_parent_dir.join("dir_2/file_3.txt/keep.lock"), "")
+        .expect("[ERROR]: failed to write the file");
+
+    let single = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        continue_on_error: true,
+        filters: vec![FilterRule::Exclude("*.lock".to_string())],
+        ..Default::default()
+    };
+    let parallel = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        continue_on_error: true,
+        jobs: 4,
+        filters: vec![FilterRule::Exclude("*.lock".to_string())],
+        ..Default::default()
+    };
+
+    let single_plan = single.plan().expect("[ERROR]: failed to plan the sync");
+    let parallel_plan = parallel.plan().expect("[ERROR]: failed to plan the sync");
+    assert_eq!(single_plan.operations, parallel_plan.operations);
+
+    let report = parallel
+        .apply_plan(&parallel_plan)
+        .expect("[ERROR]: failed to apply the plan");
+    assert_eq!(report.applied, parallel_plan.len() - 1);
+    assert_eq!(report.failures.len(), 1);
+
+    for dir in 0..4 {
+        for file in 0..8 {
+            let relative = format!("dir_{}/file_{}.txt", dir, file);
+            if relative == "dir_2/file_3.txt" {
+                continue;
+            }
+            let content = read_to_string(dest_parent_dir.join(&relative))
+                .expect("[ERROR]: failed to read the destination file");
+            assert_eq!(content, format!("{}-{}", dir, file));
+        }
+    }
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_atomic_update() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_atomic_update");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_atomic_update");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(dest_parent_dir.join("data.txt"), "old").expect("[ERROR]: failed to write the file");
+    write(dest_parent_dir.join("locked.txt"), "kept").expect("[ERROR]: failed to write the file");
+    thread::sleep(time::Duration::from_millis(20));
+    write(src_parent_dir.join("data.txt"), "new").expect("[ERROR]: failed to write the file");
+    write(src_parent_dir.join("locked.txt"), "lost").expect("[ERROR]: failed to write the file");
+
+    // An unreadable source fails the update, the destination must keep the old file
+    let locked = src_parent_dir.join("locked.txt");
+    std::fs::set_permissions(&locked, Permissions::from_mode(0o000))
+        .expect("[ERROR]: failed to set the permissions");
+    let unreadable = File::open(&locked).is_err();
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        continue_on_error: true,
+        ..Default::default()
+    };
+
+    let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    let report = sync
+        .apply_plan(&plan)
+        .expect("[ERROR]: failed to apply the plan");
+
+    let content = read_to_string(dest_parent_dir.join("data.txt"))
+        .expect("[ERROR]: failed to read the destination file");
+    assert_eq!(content, "new");
+
+    if unreadable {
+        assert_eq!(report.failures.len(), 1);
+        let content = read_to_string(dest_parent_dir.join("locked.txt"))
+            .expect("[ERROR]: failed to read the destination file");
+        assert_eq!(content, "kept");
+    }
+
+    let leftovers = read_dir(&dest_parent_dir)
+        .expect("[ERROR]: failed to read the destination")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".cover-tmp"))
+        .count();
+    assert_eq!(leftovers, 0);
+
+    std::fs::set_permissions(&locked, Permissions::from_mode(0o644))
+        .expect("[ERROR]: failed to set the permissions");
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_move_detection() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_move_detection");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_move_detection");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(src_parent_dir.join("media"))
+        .expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join("media/clip.bin"), "large media content")
+        .expect("[ERROR]: failed to write the file");
+    write(dest_parent_dir.join("clip.bin"), "large media content")
+        .expect("[ERROR]: failed to write the file");
+    // Same size but different content is not a move
+    write(src_parent_dir.join("other.txt"), "aaaa").expect("[ERROR]: failed to write the file");
+    write(dest_parent_dir.join("gone.txt"), "bbbb").expect("[ERROR]: failed to write the file");
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+
+    let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert!(plan.operations.contains(&SyncOperation {
+        kind: OperationKind::Move,
+        path: PathBuf::from("media/clip.bin"),
+        size: 19,
+        from: Some(PathBuf::from("clip.bin")),
+    }));
+    assert!(
+        plan.iter()
+            .any(|op| op.kind == OperationKind::Create && op.path == Path::new("other.txt"))
+    );
+    assert!(
+        plan.iter()
+            .any(|op| op.kind == OperationKind::Delete && op.path == Path::new("gone.txt"))
+    );
+    assert!(!plan.iter().any(|op| op.path == Path::new("clip.bin")));
+    assert_eq!(plan.transfer_size(), 4);
+
+    let report = sync
+        .apply_plan(&plan)
+        .expect("[ERROR]: failed to apply the plan");
+    assert!(report.is_success());
+    assert!(!dest_parent_dir.join("clip.bin").exists());
+    let content = read_to_string(dest_parent_dir.join("media/clip.bin"))
+        .expect("[ERROR]: failed to read the destination file");
+    assert_eq!(content, "large media content");
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .is_empty()
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_delta_update() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_delta_update");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_delta_update");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+
+    let original: Vec<u8> = (0..256 * 1024u32).map(|i| (i * 7 % 251) as u8).collect();
+    let src_file = src_parent_dir.join("disk.img");
+    let dest_file = dest_parent_dir.join("disk.img");
+
+    let mut edited = original.clone();
+    edited[100_000..100_100].fill(0xff);
+    edited.extend_from_slice(b"appended log line");
+    let mut inserted = b"shifted".to_vec();
+    inserted.extend_from_slice(&original);
+    let truncated = original[..50_000].to_vec();
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        delta_threshold: Some(1024),
+        inplace: true,
+        ..Default::default()
+    };
+
+    for new_content in [&edited, &inserted, &truncated] {
+        write(&dest_file, &original).expect("[ERROR]: failed to write the file");
+        write(&src_file, new_content).expect("[ERROR]: failed to write the file");
+
+        sync.delta_update(&src_file, &dest_file)
+            .expect("[ERROR]: failed to update the file");
+        let content = std::fs::read(&dest_file).expect("[ERROR]: failed to read the file");
+        assert!(content == *new_content);
+    }
+
+    write(&dest_file, &original).expect("[ERROR]: failed to write the file");
+    thread::sleep(time::Duration::from_millis(20));
+    write(&src_file, &edited).expect("[ERROR]: failed to write the file");
+
+    let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert!(plan.contains(OperationKind::Update));
+    let inode = metadata(&dest_file).expect("[ERROR]: failed to read").ino();
+    let report = sync
+        .apply_plan(&plan)
+        .expect("[ERROR]: failed to apply the plan");
+    assert!(report.bytes_saved > 200 * 1024, "{}", report.bytes_saved);
+    let content = std::fs::read(&dest_file).expect("[ERROR]: failed to read the file");
+    assert!(content == edited);
+    assert_eq!(
+        metadata(&dest_file).expect("[ERROR]: failed to read").ino(),
+        inode
+    );
+
+    // Without `inplace` the file is written to a temporary sibling and renamed over the old one
+    let atomic = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        delta_threshold: Some(1024),
+        ..Default::default()
+    };
+    write(&dest_file, &original).expect("[ERROR]: failed to write the file");
+    thread::sleep(time::Duration::from_millis(20));
+    write(&src_file, &edited).expect("[ERROR]: failed to write the file");
+    let inode = metadata(&dest_file).expect("[ERROR]: failed to read").ino();
+    let plan = atomic.plan().expect("[ERROR]: failed to plan the sync");
+    let report = atomic
+        .apply_plan(&plan)
+        .expect("[ERROR]: failed to apply the plan");
+    assert_eq!(report.bytes_saved, 0);
+    assert!(std::fs::read(&dest_file).expect("[ERROR]: failed to read the file") == edited);
+    assert_ne!(
+        metadata(&dest_file).expect("[ERROR]: failed to read").ino(),
+        inode
+    );
+
+    let whole = SyncData {
+        whole_file: true,
+        ..sync
+    };
+    write(&dest_file, &original).expect("[ERROR]: failed to write the file");
+    thread::sleep(time::Duration::from_millis(20));
+    write(&src_file, &edited).expect("[ERROR]: failed to write the file");
+    let plan = whole.plan().expect("[ERROR]: failed to plan the sync");
+    let report = whole
+        .apply_plan(&plan)
+        .expect("[ERROR]: failed to apply the plan");
+    assert_eq!(report.bytes_saved, 0);
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_exclude_and_coverignore() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_exclude_patterns");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_exclude_patterns");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(src_parent_dir.join("node_modules/pkg"))
+        .expect("[ERROR]: failed to create a source directory");
+    create_dir_all(src_parent_dir.join("logs")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(dest_parent_dir.join("target/debug"))
+        .expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join("main.rs"), "fn main() {}").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("scratch.tmp"), "tmp").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("keep.tmp"), "keep").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("node_modules/pkg/index.js"), "js")
+        .expect("[ERROR]: failed to write");
+    write(
+        src_parent_dir.join(".coverignore"),
+        "# build output\n/target/\n",
+    )
+    .expect("[ERROR]: failed to write");
+    write(
+        src_parent_dir.join("logs/.coverignore"),
+        "!important.log\n*.log\n",
+    )
+    .expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("logs/debug.log"), "debug").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("logs/important.log"), "important")
+        .expect("[ERROR]: failed to write");
+    // Excluded paths in the destination must survive the sync
+    write(dest_parent_dir.join("target/debug/app"), "binary").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("old.tmp"), "old").expect("[ERROR]: failed to write");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        filters: vec![
+            FilterRule::Include("keep.tmp".to_string()),
+            FilterRule::Exclude("*.tmp".to_string()),
+            FilterRule::Exclude("node_modules/".to_string()),
+        ],
+        ..Default::default()
+    };
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert!(dest_parent_dir.join("main.rs").exists());
+    assert!(dest_parent_dir.join("keep.tmp").exists());
+    assert!(dest_parent_dir.join("logs/important.log").exists());
+    assert!(!dest_parent_dir.join("scratch.tmp").exists());
+    assert!(!dest_parent_dir.join("node_modules").exists());
+    assert!(!dest_parent_dir.join("logs/debug.log").exists());
+    assert!(dest_parent_dir.join("old.tmp").exists());
+    assert!(dest_parent_dir.join("target/debug/app").exists());
+
+    let invalid = SyncData {
+        filters: vec![FilterRule::Exclude("[".to_string())],
+        ..Default::default()
+    };
+    assert!(matches!(
+        invalid.path_filter(),
+        Err(Error::InvalidPattern { .. })
+    ));
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_respect_gitignore() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_respect_gitignore");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_respect_gitignore");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(src_parent_dir.join(".git/info"))
+        .expect("[ERROR]: failed to create a source directory");
+    create_dir_all(src_parent_dir.join("build")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(src_parent_dir.join("logs")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join(".git/HEAD"), "ref: refs/heads/main")
+        .expect("[ERROR]: failed to write");
+    write(src_parent_dir.join(".git/info/exclude"), "notes.txt\n")
+        .expect("[ERROR]: failed to write");
+    write(
+        src_parent_dir.join(".gitignore"),
+        "*.log\n!keep.log\nbuild/\n",
+    )
+    .expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("logs/.gitignore"), "!trace.log\n")
+        .expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("main.rs"), "fn main() {}").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("notes.txt"), "notes").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("keep.log"), "keep").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("build/app"), "binary").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("logs/debug.log"), "debug").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("logs/trace.log"), "trace").expect("[ERROR]: failed to write");
+
+    // The .git directory is only left out by its own switch
+    let listing = SyncData {
+        source: src_parent_dir.clone(),
+        respect_gitignore: true,
+        ..Default::default()
+    };
+    let listed = listing
+        .list_src_files()
+        .expect("[ERROR]: failed to list the files");
+    assert!(listed.contains(&src_parent_dir.join(".git/HEAD")));
+    assert!(!listed.contains(&src_parent_dir.join("notes.txt")));
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        respect_gitignore: true,
+        exclude_git_dir: true,
+        ..Default::default()
+    };
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert!(dest_parent_dir.join("main.rs").exists());
+    assert!(dest_parent_dir.join("keep.log").exists());
+    assert!(dest_parent_dir.join("logs/trace.log").exists());
+    assert!(dest_parent_dir.join(".gitignore").exists());
+    assert!(!dest_parent_dir.join("logs/debug.log").exists());
+    assert!(!dest_parent_dir.join("build").exists());
+    assert!(!dest_parent_dir.join("notes.txt").exists());
+    assert!(!dest_parent_dir.join(".git").exists());
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_size_age_and_type_filters() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_size_age_type");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_size_age_type");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join("report.pdf"), "report").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("notes.docx"), "notes").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("photo.png"), "photo").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("old.docx"), "old").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("big.pdf"), vec![b'b'; 4096]).expect("[ERROR]: failed to write");
+    // The source decides for both sides, so the small copy of a big file is kept as it is
+    write(dest_parent_dir.join("big.pdf"), "small copy").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("stale.pdf"), "stale").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("huge.pdf"), vec![b'h'; 4096]).expect("[ERROR]: failed to write");
+
+    let old_time = UNIX_EPOCH + time::Duration::from_secs(1_600_000_000);
+    File::options()
+        .write(true)
+        .open(src_parent_dir.join("old.docx"))
+        .and_then(|f| f.set_times(FileTimes::new().set_modified(old_time)))
+        .expect("[ERROR]: failed to set the file times");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        max_size: Some(1024),
+        newer_than: Some(time::Duration::from_secs(7 * 24 * 60 * 60)),
+        extensions: vec!["pdf".to_string()],
+        file_types: vec![FileType::Document],
+        ..Default::default()
+    };
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert!(dest_parent_dir.join("report.pdf").exists());
+    assert!(dest_parent_dir.join("notes.docx").exists());
+    assert!(!dest_parent_dir.join("photo.png").exists());
+    assert!(!dest_parent_dir.join("old.docx").exists());
+    assert!(!dest_parent_dir.join("stale.pdf").exists());
+    assert!(dest_parent_dir.join("huge.pdf").exists());
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("big.pdf")).expect("[ERROR]: failed to read"),
+        "small copy"
+    );
+
+    let listed = sync
+        .list_src_files()
+        .expect("[ERROR]: failed to list the files");
+    assert_eq!(listed.len(), 2);
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_link_policies() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_link_policies");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_link_policies");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(src_parent_dir.join("dir")).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("file.txt"), "file").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("dir/inner.txt"), "inner").expect("[ERROR]: failed to write");
+    symlink("file.txt", src_parent_dir.join("relative")).expect("[ERROR]: failed to link");
+    symlink(
+        src_parent_dir.join("dir/inner.txt"),
+        src_parent_dir.join("absolute"),
+    )
+    .expect("[ERROR]: failed to link");
+    symlink("..", src_parent_dir.join("dir/back")).expect("[ERROR]: failed to link");
+    symlink("second", src_parent_dir.join("first")).expect("[ERROR]: failed to link");
+    symlink("first", src_parent_dir.join("second")).expect("[ERROR]: failed to link");
+
+    // Links are recreated, absolute links inside the source are made relative
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    let mut preserve = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        links: LinkPolicy::Preserve,
+        ..Default::default()
+    };
+    preserve.sync_output().expect("[ERROR]: failed to sync");
+
+    let target = |path: &str| read_link(dest_parent_dir.join(path)).ok();
+    assert_eq!(target("relative"), Some(PathBuf::from("file.txt")));
+    assert_eq!(target("absolute"), Some(PathBuf::from("dir/inner.txt")));
+    assert_eq!(target("dir/back"), Some(PathBuf::from("..")));
+    assert_eq!(target("first"), Some(PathBuf::from("second")));
+    assert!(preserve.plan().expect("[ERROR]: failed to plan").is_empty());
+
+    // Following the links copies their targets and reports the loops
+    remove_dir_all(&dest_parent_dir).ok();
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    let mut follow = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        links: LinkPolicy::Follow,
+        ..Default::default()
+    };
+    let index = follow.scan().expect("[ERROR]: failed to scan");
+    let skipped: Vec<&PathBuf> = index.source.skipped.iter().map(|(path, _)| path).collect();
+    assert!(skipped.contains(&&src_parent_dir.join("dir/back")));
+    assert!(skipped.contains(&&src_parent_dir.join("first")));
+    assert!(skipped.contains(&&src_parent_dir.join("second")));
+
+    follow.sync_output().expect("[ERROR]: failed to sync");
+    assert!(target("relative").is_none());
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("absolute")).expect("[ERROR]: failed to read"),
+        "inner"
+    );
+    assert!(!dest_parent_dir.join("dir/back").exists());
+
+    // Skipped links are neither copied nor removed
+    remove_dir_all(&dest_parent_dir).ok();
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    symlink("file.txt", dest_parent_dir.join("kept")).expect("[ERROR]: failed to link");
+    let mut skip = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        links: LinkPolicy::Skip,
+        ..Default::default()
+    };
+    skip.sync_output().expect("[ERROR]: failed to sync");
+    assert!(dest_parent_dir.join("file.txt").exists());
+    assert!(target("relative").is_none() && !dest_parent_dir.join("relative").exists());
+    assert_eq!(target("kept"), Some(PathBuf::from("file.txt")));
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_archive_metadata() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_archive_metadata");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_archive_metadata");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    let src_dir = src_parent_dir.join("service");
+    let src_file = src_dir.join("data.db");
+    create_dir_all(&src_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(&src_file, "data").expect("[ERROR]: failed to write");
+    xattr::set(&src_file, "user.label", b"service").expect("[ERROR]: failed to set an xattr");
+    set_permissions(&src_dir, Permissions::from_mode(0o750))
+        .expect("[ERROR]: failed to set the permissions");
+    if is_root() {
+        chown(&src_file, Some(1234), Some(4321)).expect("[ERROR]: failed to change the owner");
+    }
+
+    let old_time = UNIX_EPOCH + time::Duration::from_secs(1_600_000_000);
+    File::open(&src_dir)
+        .and_then(|f| f.set_times(FileTimes::new().set_modified(old_time)))
+        .expect("[ERROR]: failed to set the directory times");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        archive: true,
+        ..Default::default()
+    };
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    let dest_dir = dest_parent_dir.join("service");
+    let dest_file = dest_dir.join("data.db");
+    let dest_meta = metadata(&dest_dir).expect("[ERROR]: failed to read the metadata");
+    assert_eq!(dest_meta.permissions().mode() & 0o7777, 0o750);
+    assert_eq!(dest_meta.modified().ok(), Some(old_time));
+    assert_eq!(
+        xattr::get(&dest_file, "user.label").expect("[ERROR]: failed to get an xattr"),
+        Some(b"service".to_vec())
+    );
+    if is_root() {
+        let file_meta = metadata(&dest_file).expect("[ERROR]: failed to read the metadata");
+        assert_eq!((file_meta.uid(), file_meta.gid()), (1234, 4321));
+    }
+    assert!(sync.plan().expect("[ERROR]: failed to plan").is_empty());
+
+    // A change of the metadata alone is synced without copying the content
+    xattr::set(&src_file, "user.label", b"changed").expect("[ERROR]: failed to set an xattr");
+    let plan = sync.plan().expect("[ERROR]: failed to plan");
+    let ops: Vec<(OperationKind, &Path)> =
+        plan.iter().map(|op| (op.kind, op.path.as_path())).collect();
+    assert_eq!(
+        ops,
+        vec![(OperationKind::Attrs, Path::new("service/data.db"))]
+    );
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert_eq!(
+        xattr::get(&dest_file, "user.label").expect("[ERROR]: failed to get an xattr"),
+        Some(b"changed".to_vec())
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_hard_link_groups() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_hard_link_groups");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_hard_link_groups");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(src_parent_dir.join("sub")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join("a.bin"), "package").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("solo.txt"), "solo").expect("[ERROR]: failed to write");
+    hard_link(src_parent_dir.join("a.bin"), src_parent_dir.join("b.bin"))
+        .expect("[ERROR]: failed to link");
+    hard_link(
+        src_parent_dir.join("a.bin"),
+        src_parent_dir.join("sub/c.bin"),
+    )
+    .expect("[ERROR]: failed to link");
+
+    let inode = |path: &str| {
+        metadata(dest_parent_dir.join(path))
+            .expect("[ERROR]: failed to read the metadata")
+            .ino()
+    };
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        hard_links: true,
+        ..Default::default()
+    };
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert_eq!(inode("a.bin"), inode("b.bin"));
+    assert_eq!(inode("a.bin"), inode("sub/c.bin"));
+    assert_ne!(inode("a.bin"), inode("solo.txt"));
+    assert!(sync.plan().expect("[ERROR]: failed to plan").is_empty());
+
+    // An update of the group copies the content once and links the rest to it again
+    thread::sleep(time::Duration::from_millis(20));
+    write(src_parent_dir.join("a.bin"), "package v2").expect("[ERROR]: failed to write");
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert_eq!(inode("a.bin"), inode("b.bin"));
+    assert_eq!(inode("a.bin"), inode("sub/c.bin"));
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("sub/c.bin")).expect("[ERROR]: failed to read"),
+        "package v2"
+    );
+
+    // Removing one path of the group leaves the others linked
+    remove_file(src_parent_dir.join("sub/c.bin")).expect("[ERROR]: failed to remove");
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert!(!dest_parent_dir.join("sub/c.bin").exists());
+    assert_eq!(inode("a.bin"), inode("b.bin"));
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_sparse_copy_and_delta() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_sparse_copy");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_sparse_copy");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+
+    let len: u64 = 8 * 1024 * 1024;
+    let src_file = src_parent_dir.join("disk.img");
+    let dest_file = dest_parent_dir.join("disk.img");
+    let write_at = |path: &Path, offset: u64, data: &[u8]| {
+        let mut file = File::options()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .expect("[ERROR]: failed to open the file");
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.write_all(data))
+            .expect("[ERROR]: failed to write");
+    };
+    File::create(&src_file)
+        .and_then(|file| file.set_len(len))
+        .expect("[ERROR]: failed to create the file");
+    write_at(&src_file, 0, &[b'h'; 4096]);
+    write_at(&src_file, 5 * 1024 * 1024, &[b'd'; 4096]);
+
+    let allocated = |path: &Path| metadata(path).expect("[ERROR]: failed to read").blocks() * 512;
+    // Holes are only checked where the file system supports them
+    let holes = allocated(&src_file) < len;
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        delta_threshold: Some(1024 * 1024),
+        inplace: true,
+        ..Default::default()
+    };
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    let src_content = std::fs::read(&src_file).expect("[ERROR]: failed to read");
+    assert_eq!(std::fs::read(&dest_file).ok(), Some(src_content.clone()));
+    if holes {
+        assert!(allocated(&dest_file) < len / 2);
+    }
+
+    // The delta update punches the holes back into a destination that was filled in
+    write(&dest_file, &src_content).expect("[ERROR]: failed to write");
+    thread::sleep(time::Duration::from_millis(20));
+    write_at(&src_file, 6 * 1024 * 1024, &[b'n'; 4096]);
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert_eq!(
+        std::fs::read(&dest_file).ok(),
+        Some(std::fs::read(&src_file).expect("[ERROR]: failed to read"))
+    );
+    if holes {
+        assert!(allocated(&dest_file) < len / 2);
+    }
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_special_files() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_special_files");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_special_files");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join("file.txt"), "file").expect("[ERROR]: failed to write");
+
+    let fifo = src_parent_dir.join("queue");
+    let name = std::ffi::CString::new(fifo.as_os_str().as_bytes())
+        .expect("[ERROR]: failed to convert the path");
+    // SAFETY: the name is a valid nul terminated string for the whole call
+    assert_eq!(unsafe { libc::mkfifo(name.as_ptr(), 0o640) }, 0);
+
+    let is_fifo =
+        |path: &Path| std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_fifo());
+
+    // Skipped by default, and the skip is part of the scan
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+    let index = sync.scan().expect("[ERROR]: failed to scan");
+    assert_eq!(index.source.skipped.len(), 1);
+    assert_eq!(index.source.skipped[0].0, fifo);
+    assert!(index.source.skipped[0].1.contains("fifo"));
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert!(dest_parent_dir.join("file.txt").exists());
+    assert!(!is_fifo(&dest_parent_dir.join("queue")));
+
+    // Failing stops the sync
+    sync.specials = SpecialPolicy::Fail;
+    assert!(matches!(
+        sync.sync_output(),
+        Err(Error::SpecialFile { kind: "fifo", .. })
+    ));
+
+    // Recreating makes the FIFO again with its permissions
+    sync.specials = SpecialPolicy::Recreate;
+    let plan = sync.plan().expect("[ERROR]: failed to plan");
+    assert_eq!(
+        plan.iter().map(|op| op.kind).collect::<Vec<_>>(),
+        vec![OperationKind::Mknod]
+    );
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert!(is_fifo(&dest_parent_dir.join("queue")));
+    assert_eq!(
+        metadata(dest_parent_dir.join("queue"))
+            .expect("[ERROR]: failed to read the metadata")
+            .mode()
+            & 0o777,
+        0o640
+    );
+    assert!(sync.plan().expect("[ERROR]: failed to plan").is_empty());
+
+    // A FIFO only in the destination is removed when recreating
+    remove_file(&fifo).expect("[ERROR]: failed to remove");
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert!(!is_fifo(&dest_parent_dir.join("queue")));
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_one_file_system() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_one_file_system");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_one_file_system");
+    // Any other mounted file system will do, a followed link takes the walk into it
+    let other_dir = PathBuf::from("/dev/shm/tmpother_one_file_system");
+
+    let device = |path: &Path| metadata(path).map(|meta| meta.dev()).ok();
+    if device(Path::new("/dev/shm")).is_none()
+        || device(Path::new("/dev/shm")) == device(Path::new(&home_dir))
+    {
+        return;
+    }
+
+    for dir in [&src_parent_dir, &dest_parent_dir, &other_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    create_dir_all(&other_dir).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("local.txt"), "local").expect("[ERROR]: failed to write");
+    write(other_dir.join("remote.txt"), "remote").expect("[ERROR]: failed to write");
+    symlink(&other_dir, src_parent_dir.join("mnt")).expect("[ERROR]: failed to link");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        one_file_system: true,
+        ..Default::default()
+    };
+
+    let index = sync.scan().expect("[ERROR]: failed to scan");
+    assert!(index.source.mount_points.contains(Path::new("mnt")));
+    assert!(!index.source.contains(Path::new("mnt/remote.txt")));
+    assert_eq!(
+        sync.list_src_files()
+            .expect("[ERROR]: failed to list the files"),
+        vec![src_parent_dir.join("local.txt")]
+    );
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert!(dest_parent_dir.join("local.txt").exists());
+    assert!(!dest_parent_dir.join("mnt").exists());
+
+    // Crossing is the default
+    sync.one_file_system = false;
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert!(dest_parent_dir.join("mnt/remote.txt").exists());
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+    remove_dir_all(&other_dir).ok();
+}
+
+#[test]
+fn test_name_collisions() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_name_collisions");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_name_collisions");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    // The same name in different directories is fine everywhere
+    create_dir_all(src_parent_dir.join("a")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(src_parent_dir.join("b")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join("a/README.md"), "a").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("b/README.md"), "b").expect("[ERROR]: failed to write");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert!(dest_parent_dir.join("b/README.md").exists());
+
+    // The probe finds the way the destination treats names and leaves nothing behind
+    let folding = NameFolding::of(&dest_parent_dir).expect("[ERROR]: failed to probe");
+    let names: Vec<PathBuf> = read_dir(&dest_parent_dir)
+        .expect("[ERROR]: failed to read the directory")
+        .map(|entry| PathBuf::from(entry.expect("[ERROR]: failed to read").file_name()))
+        .collect();
+    assert!(
+        names
+            .iter()
+            .all(|name| !name.to_string_lossy().contains("cover-probe"))
+    );
+
+    write(src_parent_dir.join("a/readme.md"), "a").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("b/caf\u{e9}.txt"), "nfc").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("b/cafe\u{301}.txt"), "nfd").expect("[ERROR]: failed to write");
+    let index = sync.scan().expect("[ERROR]: failed to scan");
+
+    let case = NameFolding {
+        case: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        name_collisions(&index.source, case),
+        vec![(PathBuf::from("a/README.md"), PathBuf::from("a/readme.md"))]
+    );
+
+    let normalization = NameFolding {
+        normalization: true,
+        ..Default::default()
+    };
+    let collisions = name_collisions(&index.source, normalization);
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(
+        collision_reason(&collisions[0].0, &collisions[0].1),
+        "Unicode normalization"
+    );
+    assert_eq!(
+        collision_reason(Path::new("a/README.md"), Path::new("a/readme.md")),
+        "case"
+    );
+    assert!(name_collisions(&index.source, NameFolding::default()).is_empty());
+
+    // A destination that tells every name apart syncs them all, one that does not refuses
+    let result = sync.sync_output();
+    if folding.is_folding() {
+        assert!(matches!(result, Err(Error::NameCollisions(_))));
+    } else {
+        result.expect("[ERROR]: failed to sync");
+        assert!(dest_parent_dir.join("a/readme.md").exists());
+    }
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_non_utf8_names() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_non_utf8_names");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_non_utf8_names");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    // Two Latin-1 names that turn into the same text when they are read lossily
+    let dir = PathBuf::from(std::ffi::OsStr::from_bytes(b"r\xe9sum\xe9"));
+    let first = dir.join(std::ffi::OsStr::from_bytes(b"caf\xe9.txt"));
+    let second = dir.join(std::ffi::OsStr::from_bytes(b"caf\xe8.txt"));
+
+    create_dir_all(src_parent_dir.join(&dir)).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join(&first), "first").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join(&second), "second").expect("[ERROR]: failed to write");
+
+    assert_eq!(escaped(&first).to_string(), "r\\xe9sum\\xe9/caf\\xe9.txt");
+    assert_eq!(unescape(&escaped(&first).to_string()), Some(first.clone()));
+    let tricky = PathBuf::from(std::ffi::OsStr::from_bytes(b"a\\x41\n\xff"));
+    assert_eq!(unescape(&escaped(&tricky).to_string()), Some(tricky));
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        extensions: vec!["txt".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(sync.plan().expect("[ERROR]: failed to plan").len(), 3);
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert_eq!(
+        read_to_string(dest_parent_dir.join(&first)).expect("[ERROR]: failed to read"),
+        "first"
+    );
+    assert_eq!(
+        read_to_string(dest_parent_dir.join(&second)).expect("[ERROR]: failed to read"),
+        "second"
+    );
+    assert!(sync.plan().expect("[ERROR]: failed to plan").is_empty());
+
+    // An update goes through a temporary sibling that keeps the bytes of the name
+    thread::sleep(time::Duration::from_millis(20));
+    write(src_parent_dir.join(&second), "second v2").expect("[ERROR]: failed to write");
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert_eq!(
+        read_to_string(dest_parent_dir.join(&second)).expect("[ERROR]: failed to read"),
+        "second v2"
+    );
+    assert_eq!(
+        read_dir(dest_parent_dir.join(&dir))
+            .expect("[ERROR]: failed to read the directory")
+            .count(),
+        2
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_target_fs_names() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_target_fs_names");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_target_fs_names");
+
+    if src_parent_dir.exists() {
+        remove_dir_all(&src_parent_dir).ok();
+    }
+
+    if dest_parent_dir.exists() {
+        remove_dir_all(&dest_parent_dir).ok();
+    }
+
+    let long_name = format!("{}:::::{}.txt", "x".repeat(120), "y".repeat(120));
+    create_dir_all(src_parent_dir.join("dir:1")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    for name in [
+        "a:b.txt",
+        "what?",
+        "CON.txt",
+        "trailing..",
+        "dir:1/in|side.txt",
+        &long_name,
+    ] {
+        write(src_parent_dir.join(name), name).expect("[ERROR]: failed to write");
+    }
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        target_fs: Some(TargetFs::Fat),
+        ..Default::default()
+    };
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    for name in [
+        "a\u{f022}b.txt",
+        "what\u{f025}",
+        "\u{f143}ON.txt",
+        "trailing\u{f029}\u{f029}",
+        "dir\u{f022}1/in\u{f027}side.txt",
+    ] {
+        assert!(dest_parent_dir.join(name).exists(), "{} is missing", name);
+    }
+    let long_encoded = encode_name(std::ffi::OsStr::new(&long_name));
+    assert!(long_encoded.len() <= 255 && long_encoded.to_string_lossy().ends_with(".txt"));
+    assert!(dest_parent_dir.join(&long_encoded).exists());
+    assert_eq!(decode_name(&encode_name("CON.txt".as_ref())), "CON.txt");
+
+    // The mapped names are matched with their source names, so nothing is copied again
+    assert!(sync.plan().expect("[ERROR]: failed to plan").is_empty());
+
+    // Updates and deletes reach the mapped names by their source names, past the FAT window
+    write(src_parent_dir.join("a:b.txt"), "changed").expect("[ERROR]: failed to write");
+    File::options()
+        .write(true)
+        .open(src_parent_dir.join("a:b.txt"))
+        .and_then(|file| {
+            file.set_times(
+                FileTimes::new()
+                    .set_modified(time::SystemTime::now() + time::Duration::from_secs(5)),
+            )
+        })
+        .expect("[ERROR]: failed to set the times");
+    remove_file(src_parent_dir.join("what?")).expect("[ERROR]: failed to remove");
+    let plan = sync.plan().expect("[ERROR]: failed to plan");
+    assert_eq!(
+        plan.iter()
+            .map(|op| (op.kind, op.path.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (OperationKind::Update, PathBuf::from("a:b.txt")),
+            (OperationKind::Delete, PathBuf::from("what?"))
+        ]
+    );
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("a\u{f022}b.txt")).expect("[ERROR]: failed to read"),
+        "changed"
+    );
+    assert!(!dest_parent_dir.join("what\u{f025}").exists());
+
+    // FAT keeps the times in steps of two seconds
+    let dest_file = File::options()
+        .write(true)
+        .open(dest_parent_dir.join("CON.txt".replace('C', "\u{f143}")))
+        .expect("[ERROR]: failed to open");
+    let src_time = metadata(src_parent_dir.join("CON.txt"))
+        .and_then(|meta| meta.modified())
+        .expect("[ERROR]: failed to read the time");
+    dest_file
+        .set_times(FileTimes::new().set_modified(src_time - time::Duration::from_secs(1)))
+        .expect("[ERROR]: failed to set the times");
+    assert!(sync.plan().expect("[ERROR]: failed to plan").is_empty());
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_mirror_delete_timing() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_mirror");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_mirror");
+
+    for &timing in DeleteTiming::ALL {
+        for dir in [&src_parent_dir, &dest_parent_dir] {
+            if dir.exists() {
+                remove_dir_all(dir).ok();
+            }
+        }
+
+        create_dir_all(src_parent_dir.join("new"))
+            .expect("[ERROR]: failed to create a source directory");
+        create_dir_all(dest_parent_dir.join("old"))
+            .expect("[ERROR]: failed to create a destination directory");
+        write(src_parent_dir.join("keep.txt"), "updated").expect("[ERROR]: failed to write");
+        write(src_parent_dir.join("new/file.txt"), "new").expect("[ERROR]: failed to write");
+        write(dest_parent_dir.join("keep.txt"), "old").expect("[ERROR]: failed to write");
+        write(dest_parent_dir.join("extra.txt"), "extra").expect("[ERROR]: failed to write");
+        write(dest_parent_dir.join("old/file.txt"), "old").expect("[ERROR]: failed to write");
+        write(dest_parent_dir.join("notes.log"), "log").expect("[ERROR]: failed to write");
+        File::options()
+            .write(true)
+            .open(dest_parent_dir.join("keep.txt"))
+            .and_then(|f| f.set_times(FileTimes::new().set_modified(UNIX_EPOCH)))
+            .expect("[ERROR]: failed to set the file times");
+
+        let mut sync = SyncData {
+            source: src_parent_dir.clone(),
+            destination: dest_parent_dir.clone(),
+            mirror: true,
+            delete_timing: timing,
+            filters: vec![FilterRule::Exclude("*.log".to_string())],
+            ..Default::default()
+        };
+        sync.sync_output().expect("[ERROR]: failed to mirror");
+
+        assert_eq!(
+            read_to_string(dest_parent_dir.join("keep.txt")).expect("[ERROR]: failed to read"),
+            "updated",
+            "{:?}",
+            timing
+        );
+        assert!(
+            dest_parent_dir.join("new/file.txt").exists(),
+            "{:?}",
+            timing
+        );
+        assert!(!dest_parent_dir.join("extra.txt").exists(), "{:?}", timing);
+        assert!(!dest_parent_dir.join("old").exists(), "{:?}", timing);
+        assert!(dest_parent_dir.join("notes.log").exists(), "{:?}", timing);
+        assert!(src_parent_dir.join("keep.txt").exists(), "{:?}", timing);
+    }
+
+    // Mirroring is a command of its own
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        mirror: true,
+        changed_only: true,
+        ..Default::default()
+    };
+    assert!(!sync.single_command_selected());
+
+    // A file that fails to copy keeps the extra files, root reads every file
+    if !is_root() {
+        write(dest_parent_dir.join("extra.txt"), "extra").expect("[ERROR]: failed to write");
+        write(src_parent_dir.join("locked.txt"), "locked").expect("[ERROR]: failed to write");
+        set_permissions(
+            src_parent_dir.join("locked.txt"),
+            Permissions::from_mode(0o000),
+        )
+        .expect("[ERROR]: failed to set the permissions");
+
+        sync.changed_only = false;
+        sync.continue_on_error = true;
+        sync.delete_timing = DeleteTiming::Delay;
+        assert!(matches!(sync.sync_output(), Err(Error::Incomplete(1))));
+        assert!(dest_parent_dir.join("extra.txt").exists());
+
+        set_permissions(
+            src_parent_dir.join("locked.txt"),
+            Permissions::from_mode(0o644),
+        )
+        .expect("[ERROR]: failed to set the permissions");
+    }
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_delete_limits() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_delete_limits");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_delete_limits");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(src_parent_dir.join("new.txt"), "new").expect("[ERROR]: failed to write");
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        write(dest_parent_dir.join(name), name).expect("[ERROR]: failed to write");
+    }
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        max_delete: Some(2),
+        ..Default::default()
+    };
+
+    // Every deleting action stops before copying or removing anything
+    for action in ["changed_only", "verbose", "mirror", "delete"] {
+        sync.changed_only = action == "changed_only";
+        sync.verbose = action == "verbose";
+        sync.mirror = action == "mirror";
+        sync.delete = action == "delete";
+        assert!(
+            matches!(
+                sync.sync_output(),
+                Err(Error::MaxDelete { count: 3, limit: 2 })
+            ),
+            "{}",
+            action
+        );
+        assert!(dest_parent_dir.join("a.txt").exists(), "{}", action);
+        assert!(!dest_parent_dir.join("new.txt").exists(), "{}", action);
+    }
+
+    sync.max_delete = None;
+    sync.max_delete_percent = Some(50.0);
+    assert!(matches!(
+        sync.sync_output(),
+        Err(Error::MaxDeletePercent {
+            count: 3,
+            total: 3,
+            ..
+        })
+    ));
+    assert!(sync.check_delete_limits(1, 2).is_ok());
+    assert!(sync.check_delete_limits(2, 3).is_err());
+
+    // Within the limits the mirror goes through
+    sync.delete = false;
+    sync.mirror = true;
+    sync.max_delete = Some(3);
+    sync.max_delete_percent = Some(100.0);
+    sync.sync_output().expect("[ERROR]: failed to mirror");
+    assert!(dest_parent_dir.join("new.txt").exists());
+    assert!(!dest_parent_dir.join("a.txt").exists());
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_modified_files_continue_on_error() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_modified_errors");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_modified_errors");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    for name in ["gone.txt", "kept.txt"] {
+        write(dest_parent_dir.join(name), "old").expect("[ERROR]: failed to write");
+        File::options()
+            .write(true)
+            .open(dest_parent_dir.join(name))
+            .and_then(|f| f.set_times(FileTimes::new().set_modified(UNIX_EPOCH)))
+            .expect("[ERROR]: failed to set the file times");
+        write(src_parent_dir.join(name), "new").expect("[ERROR]: failed to write");
+    }
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+
+    // The file vanishes between the scan and the comparison
+    let index = sync.scan().expect("[ERROR]: failed to scan");
+    remove_file(dest_parent_dir.join("gone.txt")).expect("[ERROR]: failed to remove");
+
+    let mut failures = Vec::new();
+    assert!(matches!(
+        sync.src_modified_in(&index, &mut failures),
+        Err(Error::Io { .. })
+    ));
+
+    sync.continue_on_error = true;
+    let (modified, is_modified) = sync
+        .src_modified_in(&index, &mut failures)
+        .expect("[ERROR]: failed to check the modification");
+    assert_eq!(modified, vec![PathBuf::from("kept.txt")]);
+    assert!(is_modified);
+    assert_eq!(failures.len(), 1);
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_size_change_within_modify_window() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_window_size");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_window_size");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    let src_sub_file = src_parent_dir.join("common.txt");
+    let dest_sub_file = dest_parent_dir.join("common.txt");
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a source directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a destination directory");
+    write(&src_sub_file, "edited within the window").expect("[ERROR]: failed to write the file");
+    write(&dest_sub_file, "original").expect("[ERROR]: failed to write the file");
+
+    // The edit lands one second after the copy, inside the window
+    let dest_time = UNIX_EPOCH + time::Duration::from_secs(1_600_000_000);
+    let src_time = UNIX_EPOCH + time::Duration::from_secs(1_600_000_001);
+    for (file, time) in [(&src_sub_file, src_time), (&dest_sub_file, dest_time)] {
+        File::options()
+            .write(true)
+            .open(file)
+            .and_then(|f| f.set_times(FileTimes::new().set_modified(time)))
+            .expect("[ERROR]: failed to set the file times");
+    }
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        modify_window: time::Duration::from_secs(2),
+        ..Default::default()
+    };
+
+    let (modified, is_modified) = sync
+        .src_file_modified()
+        .expect("[ERROR]: failed to check the modification");
+    assert!(is_modified);
+    assert_eq!(modified, vec![PathBuf::from("common.txt")]);
+
+    let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert!(plan.contains(OperationKind::Update));
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+    assert_eq!(
+        read_to_string(&dest_sub_file).expect("[ERROR]: failed to read the destination file"),
+        "edited within the window"
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_mirror_target_fs_dest_names() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_mirror_names");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_mirror_names");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    // The destination names were not written by the sync, so they are not mapped
+    create_dir_all(src_parent_dir.join("a:b")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(dest_parent_dir.join("a:b")).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("a:b/new.txt"), "new").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("renamed.txt"), "moved content").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("old:name.txt"), "moved content").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("CON"), "extra").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("x:y"), "extra").expect("[ERROR]: failed to write");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        mirror: true,
+        target_fs: Some(TargetFs::Fat),
+        ..Default::default()
+    };
+
+    let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert!(plan.iter().any(|op| op.kind == OperationKind::Move
+        && op.from.as_deref() == Some(Path::new("old:name.txt"))));
+
+    sync.sync_output().expect("[ERROR]: failed to mirror");
+
+    assert!(!dest_parent_dir.join("CON").exists());
+    assert!(!dest_parent_dir.join("x:y").exists());
+    assert!(!dest_parent_dir.join("old:name.txt").exists());
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("renamed.txt")).expect("[ERROR]: failed to read"),
+        "moved content"
+    );
+    assert!(dest_parent_dir.join("a:b/new.txt").exists());
+
+    // A second run finds nothing to do
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .is_empty()
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_file_replaced_by_directory() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_file_to_dir");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_file_to_dir");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    // The destination file "x" has the content of the new source file, so it would be a move source
+    create_dir_all(src_parent_dir.join("x")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("x/inner.txt"), "inner").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("renamed.txt"), "old file").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("x"), "old file").expect("[ERROR]: failed to write");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+
+    let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert_eq!(plan.operations[0].kind, OperationKind::Replace);
+    assert_eq!(plan.operations[0].path, PathBuf::from("x"));
+    assert!(!plan.iter().any(|op| op.kind == OperationKind::Move));
+    assert!(!plan.iter().any(|op| op.kind == OperationKind::Delete));
+
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert!(dest_parent_dir.join("x").is_dir());
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("x/inner.txt")).expect("[ERROR]: failed to read"),
+        "inner"
+    );
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("renamed.txt")).expect("[ERROR]: failed to read"),
+        "old file"
+    );
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .is_empty()
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_directory_replaced_by_file() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_dir_to_file");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_dir_to_file");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a directory");
+    create_dir_all(dest_parent_dir.join("y/sub")).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("y"), "now a file").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("y/inner.txt"), "inner").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("y/sub/deep.txt"), "deep").expect("[ERROR]: failed to write");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        mirror: true,
+        ..Default::default()
+    };
+
+    let plan = sync.plan().expect("[ERROR]: failed to plan the sync");
+    assert_eq!(plan.operations[0].kind, OperationKind::Replace);
+    assert_eq!(plan.operations[0].path, PathBuf::from("y"));
+    assert!(
+        !plan
+            .iter()
+            .any(|op| matches!(op.kind, OperationKind::Delete | OperationKind::Rmdir))
+    );
+
+    // The replaced directory counts with everything in it
+    sync.max_delete = Some(3);
+    assert!(sync.sync_output().is_err());
+    assert!(dest_parent_dir.join("y/sub/deep.txt").exists());
+
+    sync.max_delete = None;
+    sync.sync_output().expect("[ERROR]: failed to mirror");
+
+    assert!(dest_parent_dir.join("y").is_file());
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("y")).expect("[ERROR]: failed to read"),
+        "now a file"
+    );
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .is_empty()
+    );
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_dry_run_name_folding() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_dry_run_folding");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_dry_run_folding");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("README.md"), "a").expect("[ERROR]: failed to write");
+    write(src_parent_dir.join("readme.md"), "b").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("Caf\u{e9}.txt"), "c").expect("[ERROR]: failed to write");
+
+    let past = UNIX_EPOCH + time::Duration::new(1_600_000_000, 0);
+    File::open(&dest_parent_dir)
+        .and_then(|dir| dir.set_times(FileTimes::new().set_modified(past)))
+        .expect("[ERROR]: failed to set the directory times");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        dry_run: true,
+        ..Default::default()
+    };
+
+    // A dry run writes no probe, so the destination keeps its timestamp
+    let index = sync.scan().expect("[ERROR]: failed to scan");
+    let inferred = NameFolding::from_entries(&index.destination);
+    let result = sync.sync_output();
+    assert_eq!(
+        metadata(&dest_parent_dir)
+            .and_then(|meta| meta.modified())
+            .expect("[ERROR]: failed to read the directory times"),
+        past
+    );
+
+    // The entries lead to the same folding the probe finds
+    let probed = NameFolding::of(&dest_parent_dir).expect("[ERROR]: failed to probe");
+    assert_eq!(inferred, probed);
+    match probed.case {
+        true => assert!(matches!(result, Err(Error::NameCollisions(1)))),
+        false => result.expect("[ERROR]: failed to print the plan"),
+    }
+
+    // A probe that can not be created is reported
+    assert!(matches!(
+        NameFolding::of(&dest_parent_dir.join("missing")),
+        Err(Error::Io { .. })
+    ));
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_verbose_target_fs_updates_dest_names() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_verbose_names");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_verbose_names");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    // The destination name was not written by the sync, so it is not mapped
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a directory");
+    write(dest_parent_dir.join("x:y"), "old").expect("[ERROR]: failed to write");
+    thread::sleep(time::Duration::from_millis(20));
+    write(src_parent_dir.join("x:y"), "new content").expect("[ERROR]: failed to write");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        verbose: true,
+        target_fs: Some(TargetFs::Fat),
+        ..Default::default()
+    };
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("x:y")).expect("[ERROR]: failed to read"),
+        "new content"
+    );
+    assert!(!dest_parent_dir.join("x\u{f022}y").exists());
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_target_fs_dest_name_taken() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_dest_name_taken");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_dest_name_taken");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    // Both destination names are mapped back to "x:y"
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("x:y"), "new").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("x:y"), "old").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("x\u{f022}y"), "mapped").expect("[ERROR]: failed to write");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        target_fs: Some(TargetFs::Fat),
+        ..Default::default()
+    };
+    assert!(matches!(sync.scan(), Err(Error::DestNameTaken { .. })));
+
+    sync.continue_on_error = true;
+    let index = sync.scan().expect("[ERROR]: failed to scan");
+    assert_eq!(index.failures.len(), 1);
+    assert!(matches!(index.failures[0], Error::DestNameTaken { .. }));
+    assert_eq!(index.destination.files().count(), 1);
+    assert!(matches!(sync.sync_output(), Err(Error::Incomplete(1))));
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_copy_and_remove_helpers_with_rename() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_helpers_rename");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_helpers_rename");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    // The same content under a new name is planned as a move by the sync
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("new_name.bin"), "same content").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("old_name.bin"), "same content").expect("[ERROR]: failed to write");
+
+    let sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        ..Default::default()
+    };
+    assert!(
+        sync.plan()
+            .expect("[ERROR]: failed to plan the sync")
+            .contains(OperationKind::Move)
+    );
+
+    // Copying leaves the old file where it is
+    sync.copy_src_to_dest()
+        .expect("[ERROR]: failed to copy the files");
+    assert_eq!(
+        read_to_string(dest_parent_dir.join("new_name.bin")).expect("[ERROR]: failed to read"),
+        "same content"
+    );
+    assert!(dest_parent_dir.join("old_name.bin").exists());
+
+    sync.remove_dest_file()
+        .expect("[ERROR]: failed to remove the files");
+    assert!(dest_parent_dir.join("new_name.bin").exists());
+    assert!(!dest_parent_dir.join("old_name.bin").exists());
+
+    // Removing alone takes the old path away as well
+    write(dest_parent_dir.join("old_name.bin"), "same content").expect("[ERROR]: failed to write");
+    remove_file(dest_parent_dir.join("new_name.bin")).expect("[ERROR]: failed to remove");
+    sync.remove_dest_file()
+        .expect("[ERROR]: failed to remove the files");
+    assert!(!dest_parent_dir.join("old_name.bin").exists());
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_archive_preserved_link_metadata() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_archive_links");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_archive_links");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    create_dir_all(&src_parent_dir).expect("[ERROR]: failed to create a directory");
+    create_dir_all(&dest_parent_dir).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("target.txt"), "target").expect("[ERROR]: failed to write");
+    let src_link = src_parent_dir.join("link");
+    symlink("target.txt", &src_link).expect("[ERROR]: failed to create a link");
+    if is_root() {
+        lchown(&src_link, Some(1234), Some(4321)).expect("[ERROR]: failed to change the owner");
+    }
+    // The copy is made later, so its own timestamp would differ
+    thread::sleep(time::Duration::from_millis(20));
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        archive: true,
+        links: LinkPolicy::Preserve,
+        ..Default::default()
+    };
+    sync.sync_output().expect("[ERROR]: failed to sync");
+
+    let link_meta = |path: &Path| {
+        let meta = std::fs::symlink_metadata(path).expect("[ERROR]: failed to read the metadata");
+        (meta.uid(), meta.gid(), meta.mtime(), meta.mtime_nsec())
+    };
+    let dest_link = dest_parent_dir.join("link");
+    assert_eq!(link_meta(&dest_link), link_meta(&src_link));
+    assert!(sync.plan().expect("[ERROR]: failed to plan").is_empty());
+
+    // A new owner of the source link alone is synced as a metadata change
+    if is_root() {
+        lchown(&src_link, Some(4321), Some(1234)).expect("[ERROR]: failed to change the owner");
+        let plan = sync.plan().expect("[ERROR]: failed to plan");
+        assert!(
+            plan.iter()
+                .any(|op| op.kind == OperationKind::Attrs && op.path == Path::new("link"))
+        );
+        sync.sync_output().expect("[ERROR]: failed to sync");
+        assert_eq!(link_meta(&dest_link), link_meta(&src_link));
+        assert_eq!(
+            read_link(&dest_link).expect("[ERROR]: failed to read the link"),
+            PathBuf::from("target.txt")
+        );
+    }
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}
+
+#[test]
+fn test_queries_report_scan_failures() {
+    let home_dir = env::var("HOME").expect("[ERROR]: failed to get the home directory");
+    let src_parent_dir = PathBuf::from(&home_dir).join("tmpsrc_query_failures");
+    let dest_parent_dir = PathBuf::from(&home_dir).join("tmpdest_query_failures");
+
+    for dir in [&src_parent_dir, &dest_parent_dir] {
+        if dir.exists() {
+            remove_dir_all(dir).ok();
+        }
+    }
+
+    // The FIFO fails the source scan and the second name of "x:y" fails the destination scan
+    create_dir_all(src_parent_dir.join("sub")).expect("[ERROR]: failed to create a directory");
+    create_dir_all(dest_parent_dir.join("sub")).expect("[ERROR]: failed to create a directory");
+    write(src_parent_dir.join("new.txt"), "new").expect("[ERROR]: failed to write");
+    let fifo = src_parent_dir.join("queue");
+    let name = std::ffi::CString::new(fifo.as_os_str().as_bytes())
+        .expect("[ERROR]: failed to convert the path");
+    // SAFETY: the name is a valid nul terminated string for the whole call
+    assert_eq!(unsafe { libc::mkfifo(name.as_ptr(), 0o640) }, 0);
+    write(dest_parent_dir.join("x:y"), "old").expect("[ERROR]: failed to write");
+    write(dest_parent_dir.join("x\u{f022}y"), "mapped").expect("[ERROR]: failed to write");
+
+    let mut sync = SyncData {
+        source: src_parent_dir.clone(),
+        destination: dest_parent_dir.clone(),
+        changed_only: true,
+        specials: SpecialPolicy::Fail,
+        target_fs: Some(TargetFs::Fat),
+        ..Default::default()
+    };
+    assert!(matches!(
+        sync.list_src_files(),
+        Err(Error::SpecialFile { .. })
+    ));
+    assert!(matches!(
+        sync.list_dest_files(),
+        Err(Error::DestNameTaken { .. })
+    ));
+
+    // While continuing on error, the queries end with the failures instead of a partial answer
+    sync.continue_on_error = true;
+    assert!(matches!(sync.list_src_files(), Err(Error::Incomplete(1))));
+    assert!(matches!(sync.list_src_dirs(), Err(Error::Incomplete(1))));
+    assert!(matches!(sync.list_dest_files(), Err(Error::Incomplete(1))));
+    assert!(matches!(sync.list_dest_dirs(), Err(Error::Incomplete(1))));
+    assert!(matches!(sync.src_file_created(), Err(Error::Incomplete(1))));
+    assert!(matches!(
+        sync.dest_file_created(),
+        Err(Error::Incomplete(1))
+    ));
+
+    remove_dir_all(&src_parent_dir).ok();
+    remove_dir_all(&dest_parent_dir).ok();
+}